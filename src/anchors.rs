@@ -0,0 +1,96 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeMap;
+use std::iter::FromIterator;
+
+use yaml_rust::scanner::Marker;
+use yaml_rust::Yaml;
+
+/// A table of anchored nodes used to resolve `Yaml::Alias` nodes.
+///
+/// The keys are the anchor ids used by `yaml-rust`'s parser events (and stored in
+/// `Yaml::Alias`). Anchored values may themselves contain aliases and merge keys; they are
+/// resolved when the alias is expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnchorTable<N = Yaml> {
+    anchors: BTreeMap<usize, N>,
+    undefined: BTreeMap<usize, (String, Marker)>,
+}
+
+impl<N> Default for AnchorTable<N> {
+    fn default() -> Self {
+        Self {
+            anchors: BTreeMap::new(),
+            undefined: BTreeMap::new(),
+        }
+    }
+}
+
+impl<N> AnchorTable<N> {
+    /// Create an empty anchor table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an anchored node to the table.
+    ///
+    /// Returns the previous node for the anchor id, if any.
+    pub fn insert(&mut self, id: usize, node: N) -> Option<N> {
+        self.anchors.insert(id, node)
+    }
+
+    /// Get the node for an anchor id.
+    pub fn get(&self, id: usize) -> Option<&N> {
+        self.anchors.get(&id)
+    }
+
+    /// Record that an alias refers to an anchor which is not defined at its use.
+    ///
+    /// Expanding an alias to this anchor id is an error which names the anchor and the position
+    /// of the alias. This takes precedence over any node inserted for the same id.
+    pub fn insert_undefined(&mut self, id: usize, anchor: String, marker: Marker) {
+        self.undefined.entry(id).or_insert((anchor, marker));
+    }
+
+    /// Get the name and position of an undefined alias to an anchor id.
+    pub fn undefined(&self, id: usize) -> Option<(&str, Marker)> {
+        self.undefined
+            .get(&id)
+            .map(|(anchor, marker)| (anchor.as_str(), *marker))
+    }
+
+    /// The number of anchors in the table.
+    pub fn len(&self) -> usize {
+        self.anchors.len()
+    }
+
+    /// Whether the table is empty or not.
+    pub fn is_empty(&self) -> bool {
+        self.anchors.is_empty()
+    }
+}
+
+impl<N> FromIterator<(usize, N)> for AnchorTable<N> {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (usize, N)>,
+    {
+        Self {
+            anchors: iter.into_iter().collect(),
+            undefined: BTreeMap::new(),
+        }
+    }
+}
+
+impl<N> Extend<(usize, N)> for AnchorTable<N> {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = (usize, N)>,
+    {
+        self.anchors.extend(iter)
+    }
+}
@@ -0,0 +1,65 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use yaml_merge_keys::{Error, ErrorKind};
+
+/// The result of checking inputs.
+///
+/// Statuses are ordered by precedence; the exit code for several inputs is that of the highest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    /// All merge keys could be handled.
+    Valid,
+    /// A merge key could not be handled.
+    MergeError,
+    /// A document is not valid YAML.
+    ParseError,
+    /// An input could not be read.
+    ReadError,
+}
+
+impl Status {
+    /// The status for an error from the library.
+    pub fn from_error(err: &Error) -> Self {
+        match err.kind() {
+            ErrorKind::Syntax => Status::ParseError,
+            _ => Status::MergeError,
+        }
+    }
+
+    /// The exit code for the status.
+    ///
+    /// Exit code 2 is used by `clap` for usage errors.
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Status::Valid => 0,
+            Status::MergeError => 1,
+            Status::ParseError => 3,
+            Status::ReadError => 4,
+        }
+    }
+}
+
+/// Format an error as a `file:line:col: message` diagnostic.
+///
+/// Errors without a known position are formatted as `file: message`.
+pub fn diagnostic(name: &str, err: &Error) -> String {
+    let msg = err.to_string();
+    if let Some(location) = err.location() {
+        // Positions are part of the diagnostic prefix already.
+        let suffix = format!(" at line {} column {}", location.line(), location.column());
+        let msg = msg.strip_suffix(&suffix).unwrap_or(&msg);
+        format!(
+            "{}:{}:{}: {}",
+            name,
+            location.line(),
+            location.column(),
+            msg,
+        )
+    } else {
+        format!("{}: {}", name, msg)
+    }
+}
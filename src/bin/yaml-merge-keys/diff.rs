@@ -0,0 +1,208 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structural differences between documents.
+//!
+//! Paths are mapping keys and sequence indices as used by `get` and `explain`. Values are shown in
+//! flow style.
+
+use std::fmt::Write;
+
+use yaml_merge_keys::{MergeKeyError, MERGE_KEY};
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Yaml, YamlEmitter};
+
+use crate::flatten::{is_merge_key, strip_document_start};
+use crate::path;
+
+/// Format a value in flow style.
+pub fn inline(value: &Yaml) -> String {
+    match value {
+        Yaml::Array(arr) => {
+            let items = arr.iter().map(inline).collect::<Vec<_>>();
+            format!("[{}]", items.join(", "))
+        },
+        Yaml::Hash(hash) => {
+            let entries = hash
+                .iter()
+                .map(|(key, value)| format!("{}: {}", inline(key), inline(value)))
+                .collect::<Vec<_>>();
+            format!("{{{}}}", entries.join(", "))
+        },
+        scalar => {
+            let mut out = String::new();
+            YamlEmitter::new(&mut out)
+                .dump(scalar)
+                .expect("scalars may be emitted");
+            strip_document_start(&out).into()
+        },
+    }
+}
+
+fn child_path(path: &str, key: &Yaml) -> String {
+    let key = match key {
+        Yaml::String(s) => path::quote(s).into_owned(),
+        key => path::quote(&inline(key)).into_owned(),
+    };
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "."
+    } else {
+        path
+    }
+}
+
+/// The keys provided by the merge sources of a mapping.
+fn merged_entries(merge_value: &Yaml) -> Result<Hash, MergeKeyError> {
+    let mut mapping = Hash::new();
+    mapping.insert(Yaml::String(MERGE_KEY.into()), merge_value.clone());
+    match yaml_merge_keys::merge_keys(Yaml::Hash(mapping))? {
+        Yaml::Hash(entries) => Ok(entries),
+        _ => unreachable!("merging into a mapping results in a mapping"),
+    }
+}
+
+fn merge_changes(
+    path: &str,
+    raw: &Yaml,
+    merged: &Yaml,
+    out: &mut String,
+) -> Result<(), MergeKeyError> {
+    match (raw, merged) {
+        (Yaml::Hash(raw), Yaml::Hash(merged)) => {
+            if let Some(merge_value) = raw.get(&Yaml::String(MERGE_KEY.into())) {
+                let sources = merged_entries(merge_value)?;
+                let _ = writeln!(out, "{}", display_path(path));
+                for (key, value) in merged.iter().filter(|(key, _)| !raw.contains_key(key)) {
+                    let _ = writeln!(out, "  + {}: {}", inline(key), inline(value));
+                }
+                for (key, value) in raw.iter().filter(|(key, _)| !is_merge_key(key)) {
+                    if let Some(source) = sources.get(key) {
+                        let _ = writeln!(
+                            out,
+                            "  ~ {}: {} (overrides {})",
+                            inline(key),
+                            inline(value),
+                            inline(source),
+                        );
+                    }
+                }
+            }
+
+            for (key, value) in raw.iter().filter(|(key, _)| !is_merge_key(key)) {
+                if let Some(merged) = merged.get(key) {
+                    merge_changes(&child_path(path, key), value, merged, out)?;
+                }
+            }
+        },
+        (Yaml::Array(raw), Yaml::Array(merged)) => {
+            for (idx, (raw, merged)) in raw.iter().zip(merged).enumerate() {
+                merge_changes(
+                    &child_path(path, &Yaml::Integer(idx as i64)),
+                    raw,
+                    merged,
+                    out,
+                )?;
+            }
+        },
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Describe the keys added and overridden by merge keys in a document.
+///
+/// The raw document has its aliases expanded, but its merge keys unresolved. Each mapping with a
+/// merge key is listed with the keys added by its merge sources (`+`) and the local keys which
+/// override a key from its merge sources (`~`).
+pub fn diff_merges(raw: &Yaml, merged: &Yaml) -> Result<String, MergeKeyError> {
+    let mut out = String::new();
+    merge_changes("", raw, merged, &mut out)?;
+    Ok(out)
+}
+
+fn value_changes(path: &str, old: &Yaml, new: &Yaml, out: &mut String) {
+    match (old, new) {
+        (Yaml::Hash(old), Yaml::Hash(new)) => {
+            for (key, value) in old {
+                let path = child_path(path, key);
+                if let Some(new) = new.get(key) {
+                    value_changes(&path, value, new, out);
+                } else {
+                    let _ = writeln!(out, "- {}: {}", path, inline(value));
+                }
+            }
+            for (key, value) in new.iter().filter(|(key, _)| !old.contains_key(key)) {
+                let _ = writeln!(out, "+ {}: {}", child_path(path, key), inline(value));
+            }
+        },
+        (Yaml::Array(old), Yaml::Array(new)) => {
+            let len = old.len().max(new.len());
+            for idx in 0..len {
+                let path = child_path(path, &Yaml::Integer(idx as i64));
+                match (old.get(idx), new.get(idx)) {
+                    (Some(old), Some(new)) => value_changes(&path, old, new, out),
+                    (Some(old), None) => {
+                        let _ = writeln!(out, "- {}: {}", path, inline(old));
+                    },
+                    (None, Some(new)) => {
+                        let _ = writeln!(out, "+ {}: {}", path, inline(new));
+                    },
+                    (None, None) => unreachable!("indices are within one of the sequences"),
+                }
+            }
+        },
+        (old, new) => {
+            if old != new {
+                let _ = writeln!(
+                    out,
+                    "~ {}: {} -> {}",
+                    display_path(path),
+                    inline(old),
+                    inline(new),
+                );
+            }
+        },
+    }
+}
+
+/// Describe the differences between two document streams.
+///
+/// Mapping key order is ignored. Removed values are marked with `-`, added values with `+`, and
+/// changed values with `~`. When either stream has multiple documents, each document with
+/// differences is introduced with a `--- document N` line.
+pub fn diff_documents(old: &[Yaml], new: &[Yaml]) -> String {
+    let mut out = String::new();
+    let multiple = old.len() > 1 || new.len() > 1;
+    let len = old.len().max(new.len());
+    for idx in 0..len {
+        let mut changes = String::new();
+        match (old.get(idx), new.get(idx)) {
+            (Some(old), Some(new)) => value_changes("", old, new, &mut changes),
+            (Some(old), None) => {
+                let _ = writeln!(changes, "- .: {}", inline(old));
+            },
+            (None, Some(new)) => {
+                let _ = writeln!(changes, "+ .: {}", inline(new));
+            },
+            (None, None) => unreachable!("indices are within one of the streams"),
+        }
+
+        if multiple && !changes.is_empty() {
+            let _ = writeln!(out, "--- document {}", idx);
+        }
+        out.push_str(&changes);
+    }
+    out
+}
@@ -0,0 +1,255 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Explain where values in a document come from.
+//!
+//! Documents are loaded with `load_marked` which keeps the position of every node and leaves
+//! aliases unexpanded so that merge sources may be named by their anchor. The value itself is
+//! taken from the document as merged by the library.
+
+use std::fmt::{self, Write};
+
+use thiserror::Error;
+use yaml_merge_keys::{AnchorTable, Marked, MarkedNode, MERGE_KEY};
+use yaml_rust::scanner::Marker;
+use yaml_rust::{Yaml, YamlEmitter};
+
+use crate::flatten::strip_document_start;
+use crate::get::lookup;
+use crate::path::{self, PathError};
+
+/// Errors which may occur when explaining a value.
+#[derive(Debug, Error)]
+pub enum ExplainError {
+    /// The document could not be loaded.
+    #[error("{}", _0)]
+    Load(#[from] yaml_merge_keys::Error),
+    /// The requested document does not exist.
+    #[error("there is no document {}", _0)]
+    NoDocument(usize),
+    /// The path does not exist in the document.
+    #[error("`{}` not found", _0)]
+    NotFound(String),
+    /// The path continues into a scalar.
+    #[error("`{}` is not a mapping or sequence", _0)]
+    NotCollection(String),
+    /// The path could not be parsed.
+    #[error("{}", _0)]
+    Path(#[from] PathError),
+}
+
+/// A position in the source.
+struct Position(Marker);
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.0.line(), self.0.col() + 1)
+    }
+}
+
+/// A merge an entry was inherited through.
+#[derive(Debug, Clone)]
+struct Hop {
+    /// The alias of the merge source or the merge key for inline merge sources.
+    marker: Marker,
+    /// The name of the anchor of the merge source.
+    anchor: Option<String>,
+}
+
+impl fmt::Display for Hop {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(anchor) = self.anchor.as_ref() {
+            write!(f, "`<<: *{}` at {}", anchor, Position(self.marker))
+        } else {
+            write!(f, "`<<` at {}", Position(self.marker))
+        }
+    }
+}
+
+/// Where a mapping entry came from.
+#[derive(Debug)]
+struct Origin<'a> {
+    /// The merges the entry was inherited through, outermost first.
+    via: Vec<Hop>,
+    key: &'a Marked,
+    value: &'a Marked,
+}
+
+impl<'a> fmt::Display for Origin<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.via.is_empty() {
+            write!(f, "local at {}", Position(self.key.marker))
+        } else {
+            write!(f, "inherited from {} through ", Position(self.key.marker))?;
+            for (idx, hop) in self.via.iter().enumerate() {
+                if idx > 0 {
+                    write!(f, " then ")?;
+                }
+                write!(f, "{}", hop)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+struct Explainer<'a> {
+    anchors: &'a AnchorTable<Marked>,
+}
+
+impl<'a> Explainer<'a> {
+    /// Resolve aliases to the node they refer to.
+    fn resolve(&self, node: &'a Marked) -> Option<&'a Marked> {
+        match node.node {
+            MarkedNode::Alias(id, _) => self.anchors.get(id).and_then(|node| self.resolve(node)),
+            _ => Some(node),
+        }
+    }
+
+    /// Gather the entries for a key in priority order.
+    ///
+    /// Local entries take precedence over merged entries and merge sources are searched in
+    /// order (including their own merge sources).
+    fn origins(
+        &self,
+        entries: &'a [(Marked, Marked)],
+        key: &str,
+        via: &mut Vec<Hop>,
+        origins: &mut Vec<Origin<'a>>,
+    ) {
+        origins.extend(
+            entries
+                .iter()
+                .filter(|(k, _)| !k.is_key(MERGE_KEY) && k.is_key(key))
+                .map(|(k, v)| {
+                    Origin {
+                        via: via.clone(),
+                        key: k,
+                        value: v,
+                    }
+                }),
+        );
+
+        for (merge_key, value) in entries.iter().filter(|(k, _)| k.is_key(MERGE_KEY)) {
+            let sources = match &value.node {
+                MarkedNode::Sequence(items) => items.iter().collect(),
+                _ => vec![value],
+            };
+
+            for source in sources {
+                let hop = if let MarkedNode::Alias(_, anchor) = &source.node {
+                    Hop {
+                        marker: source.marker,
+                        anchor: Some(anchor.clone()),
+                    }
+                } else {
+                    Hop {
+                        marker: merge_key.marker,
+                        anchor: None,
+                    }
+                };
+
+                if let Some(MarkedNode::Mapping(entries)) =
+                    self.resolve(source).map(|node| &node.node)
+                {
+                    via.push(hop);
+                    self.origins(entries, key, via, origins);
+                    via.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Format a value for display.
+fn format_value(value: &Yaml) -> String {
+    let mut out = String::new();
+    YamlEmitter::new(&mut out)
+        .dump(value)
+        .expect("merged documents may be emitted");
+    let out = strip_document_start(&out);
+
+    // Non-empty collections are emitted as blocks.
+    let is_block = match value {
+        Yaml::Array(arr) => !arr.is_empty(),
+        Yaml::Hash(hash) => !hash.is_empty(),
+        _ => false,
+    };
+    if is_block {
+        out.lines().fold(String::new(), |mut value, line| {
+            let _ = write!(value, "\n  {}", line);
+            value
+        })
+    } else {
+        format!(" {}", out)
+    }
+}
+
+/// Explain where the value at a path in a document comes from.
+///
+/// Path segments are mapping keys or sequence indices (see the `path` module).
+pub fn explain(source: &str, path: &str, document: usize) -> Result<String, ExplainError> {
+    let segments = path::parse(path)?;
+    let merged = yaml_merge_keys::load_merged(source)?;
+    let merged = merged
+        .get(document)
+        .ok_or(ExplainError::NoDocument(document))?;
+
+    let (docs, anchors) =
+        yaml_merge_keys::load_marked(source).map_err(yaml_merge_keys::Error::from)?;
+    let explainer = Explainer {
+        anchors: &anchors,
+    };
+
+    let mut value = merged;
+    let mut node = explainer.resolve(&docs[document]);
+    let mut chain = String::new();
+    for (idx, segment) in segments.iter().enumerate() {
+        let current = path::format(&segments[..=idx]);
+
+        value = lookup(value, segment).ok_or_else(|| {
+            if matches!(value, Yaml::Hash(_) | Yaml::Array(_)) {
+                ExplainError::NotFound(current.clone())
+            } else {
+                ExplainError::NotCollection(path::format(&segments[..idx]))
+            }
+        })?;
+
+        let _ = writeln!(chain, "{}", current);
+        node = match node.map(|node| &node.node) {
+            Some(MarkedNode::Mapping(entries)) => {
+                let mut origins = Vec::new();
+                explainer.origins(entries, segment, &mut Vec::new(), &mut origins);
+
+                let mut origins = origins.into_iter();
+                let winner = origins.next();
+                if let Some(winner) = winner.as_ref() {
+                    let _ = writeln!(chain, "  {}", winner);
+                }
+                for shadowed in origins {
+                    let _ = writeln!(chain, "  shadows {}", shadowed);
+                }
+
+                winner.and_then(|winner| explainer.resolve(winner.value))
+            },
+            Some(MarkedNode::Sequence(items)) => {
+                let _ = writeln!(chain, "  item {} of the sequence", segment);
+                segment
+                    .parse()
+                    .ok()
+                    .and_then(|idx: usize| items.get(idx))
+                    .and_then(|item| explainer.resolve(item))
+            },
+            _ => None,
+        };
+    }
+
+    Ok(format!(
+        "{}:{}\n{}",
+        path::format(&segments),
+        format_value(value),
+        chain,
+    ))
+}
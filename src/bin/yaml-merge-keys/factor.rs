@@ -0,0 +1,392 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Introduce anchors and merge keys into expanded documents.
+//!
+//! Mappings are visited in document order. A mapping which contains every key of an earlier
+//! mapping (which is not one of its ancestors) and ends with the entries it shares with it (in the
+//! same order) is rewritten to merge the earlier mapping. The earlier mapping with the most shared
+//! entries is used. Mappings which are equal to an earlier mapping become aliases. Requiring the
+//! shared entries to be at the end keeps the key order of the merged document the same as the
+//! input.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+use std::mem;
+
+use clap::ValueEnum;
+use thiserror::Error;
+use yaml_rust::yaml::Hash;
+use yaml_rust::Yaml;
+
+use crate::diff::inline;
+
+/// Errors which may occur when factoring documents.
+#[derive(Debug, Error)]
+pub enum FactorError {
+    /// The factored documents do not merge into the input documents.
+    #[error("the factored documents do not reproduce the input; this is a bug")]
+    RoundTrip,
+    /// The factored documents could not be loaded.
+    #[error("the factored documents could not be loaded ({}); this is a bug", _0)]
+    Reload(#[from] yaml_merge_keys::Error),
+}
+
+/// How to name introduced anchors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AnchorNames {
+    /// Use the path to the anchored mapping (e.g., `jobs_build`).
+    Path,
+    /// Number anchors in document order (e.g., `anchor1`).
+    Numbered,
+}
+
+/// Options for factoring documents.
+#[derive(Debug, Clone)]
+pub struct FactorOptions {
+    /// The minimum number of entries a merge must provide (at least 1).
+    pub min_shared: usize,
+    /// How to name anchors.
+    pub anchor_names: AnchorNames,
+    /// A prefix for anchor names.
+    pub anchor_prefix: String,
+}
+
+/// How a mapping is written.
+#[derive(Debug, Clone, Copy)]
+enum Plan {
+    /// The mapping is an alias to an equal mapping.
+    Alias(usize),
+    /// The mapping merges another mapping which provides its last `inherited` entries.
+    Merge { base: usize, inherited: usize },
+}
+
+/// A mapping which may be anchored.
+struct Candidate<'a> {
+    id: usize,
+    doc: usize,
+    path: String,
+    hash: &'a Hash,
+}
+
+/// Decides how each mapping is written.
+struct Planner<'a> {
+    min_shared: usize,
+    next_id: usize,
+    candidates: Vec<Candidate<'a>>,
+    /// The mappings containing the current node.
+    ancestors: HashSet<usize>,
+    plans: HashMap<usize, Plan>,
+    /// Mappings which are merged or aliased.
+    anchored: BTreeSet<usize>,
+}
+
+/// The number of entries a mapping would inherit by merging another mapping.
+///
+/// Every key of the base must be in the mapping and the entries with equal values must be the
+/// last entries of the mapping in the same order as in the base.
+fn inherited_entries(base: &Hash, hash: &Hash) -> Option<usize> {
+    if !base.keys().all(|key| hash.contains_key(key)) {
+        return None;
+    }
+
+    let shared = base
+        .iter()
+        .filter(|&(key, value)| hash.get(key) == Some(value))
+        .map(|(key, _)| key);
+    let count = shared.clone().count();
+    let tail = hash.keys().skip(hash.len() - count);
+    if shared.eq(tail) {
+        Some(count)
+    } else {
+        None
+    }
+}
+
+fn child_path(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.into()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+fn key_segment(key: &Yaml) -> String {
+    match key {
+        Yaml::String(s) => s.clone(),
+        key => inline(key),
+    }
+}
+
+impl<'a> Planner<'a> {
+    fn plan(&self, doc: usize, hash: &Hash) -> Option<Plan> {
+        let mut best: Option<(usize, Plan)> = None;
+        for candidate in &self.candidates {
+            if candidate.doc != doc || self.ancestors.contains(&candidate.id) {
+                continue;
+            }
+
+            let (score, plan) = if candidate.hash == hash {
+                (hash.len(), Plan::Alias(candidate.id))
+            } else if let Some(inherited) = inherited_entries(candidate.hash, hash) {
+                let plan = Plan::Merge {
+                    base: candidate.id,
+                    inherited,
+                };
+                (inherited, plan)
+            } else {
+                continue;
+            };
+
+            if score >= self.min_shared && best.as_ref().map_or(true, |(best, _)| score > *best) {
+                best = Some((score, plan));
+            }
+        }
+        best.map(|(_, plan)| plan)
+    }
+
+    fn visit(&mut self, doc: usize, path: String, node: &'a Yaml) {
+        match node {
+            Yaml::Hash(hash) => {
+                let id = self.next_id;
+                self.next_id += 1;
+
+                let plan = self.plan(doc, hash);
+                let local = match plan {
+                    Some(Plan::Alias(base)) => {
+                        self.anchored.insert(base);
+                        self.plans.insert(id, Plan::Alias(base));
+                        // Aliases may not be anchored themselves.
+                        return;
+                    },
+                    Some(Plan::Merge {
+                        base,
+                        inherited,
+                    }) => {
+                        self.anchored.insert(base);
+                        self.plans.insert(
+                            id,
+                            Plan::Merge {
+                                base,
+                                inherited,
+                            },
+                        );
+                        hash.len() - inherited
+                    },
+                    None => hash.len(),
+                };
+
+                self.candidates.push(Candidate {
+                    id,
+                    doc,
+                    path: path.clone(),
+                    hash,
+                });
+                self.ancestors.insert(id);
+                for (key, value) in hash.iter().take(local) {
+                    self.visit(doc, child_path(&path, &key_segment(key)), value);
+                }
+                self.ancestors.remove(&id);
+            },
+            Yaml::Array(arr) => {
+                for (idx, item) in arr.iter().enumerate() {
+                    self.visit(doc, child_path(&path, &idx.to_string()), item);
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Make anchor names unique and valid.
+fn anchor_names(planner: &Planner, options: &FactorOptions) -> HashMap<usize, String> {
+    let paths = planner
+        .candidates
+        .iter()
+        .map(|candidate| (candidate.id, (candidate.doc, candidate.path.as_str())))
+        .collect::<HashMap<_, _>>();
+
+    let mut used = HashSet::new();
+    planner
+        .anchored
+        .iter()
+        .enumerate()
+        .map(|(idx, &id)| {
+            let (doc, path) = paths[&id];
+            let base = match options.anchor_names {
+                AnchorNames::Path => {
+                    let path = if path.is_empty() {
+                        "root".into()
+                    } else if path.starts_with(|c: char| c.is_ascii_digit()) {
+                        // Items of a sequence at the root.
+                        format!("root_{}", path)
+                    } else {
+                        path.into()
+                    };
+                    let path = path
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                                c
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect::<String>();
+                    if doc > 0 {
+                        format!("{}doc{}_{}", options.anchor_prefix, doc, path)
+                    } else {
+                        format!("{}{}", options.anchor_prefix, path)
+                    }
+                },
+                AnchorNames::Numbered => format!("{}anchor{}", options.anchor_prefix, idx + 1),
+            };
+
+            let mut name = base.clone();
+            let mut suffix = 1;
+            while !used.insert(name.clone()) {
+                suffix += 1;
+                name = format!("{}_{}", base, suffix);
+            }
+            (id, name)
+        })
+        .collect()
+}
+
+/// Writes factored documents.
+struct Writer<'a> {
+    plans: &'a HashMap<usize, Plan>,
+    names: &'a HashMap<usize, String>,
+    next_id: usize,
+    /// Whether the next line continues after a sequence indicator.
+    compact: bool,
+    out: String,
+}
+
+impl<'a> Writer<'a> {
+    fn indent(&mut self, indent: usize) {
+        if mem::take(&mut self.compact) {
+            self.out.push(' ');
+        } else {
+            self.out.push_str(&" ".repeat(indent));
+        }
+    }
+
+    fn key(&mut self, key: &Yaml, indent: usize) {
+        if let Yaml::Array(_) | Yaml::Hash(_) = key {
+            let _ = writeln!(self.out, "? {}", inline(key));
+            self.indent(indent);
+            self.out.push(':');
+        } else {
+            let _ = write!(self.out, "{}:", inline(key));
+        }
+    }
+
+    /// Write a node after an indicator (`key:`, `-`, or `---`) on the current line.
+    fn node(&mut self, node: &Yaml, indent: usize) {
+        let compact = mem::take(&mut self.compact);
+        match node {
+            Yaml::Hash(hash) => {
+                let id = self.next_id;
+                self.next_id += 1;
+
+                let (merge, local) = match self.plans.get(&id) {
+                    Some(Plan::Alias(base)) => {
+                        let _ = writeln!(self.out, " *{}", self.names[base]);
+                        return;
+                    },
+                    Some(&Plan::Merge {
+                        base,
+                        inherited,
+                    }) => (Some(base), hash.len() - inherited),
+                    None => (None, hash.len()),
+                };
+
+                let anchor = self.names.get(&id);
+                if let Some(name) = anchor {
+                    let _ = write!(self.out, " &{}", name);
+                }
+                if merge.is_none() && local == 0 {
+                    self.out.push_str(" {}\n");
+                    return;
+                }
+                if compact && anchor.is_none() {
+                    self.compact = true;
+                } else {
+                    self.out.push('\n');
+                }
+
+                if let Some(base) = merge {
+                    self.indent(indent);
+                    let _ = writeln!(self.out, "<<: *{}", self.names[&base]);
+                }
+                for (key, value) in hash.iter().take(local) {
+                    self.indent(indent);
+                    self.key(key, indent);
+                    self.node(value, indent + 2);
+                }
+            },
+            Yaml::Array(arr) => {
+                if arr.is_empty() {
+                    self.out.push_str(" []\n");
+                    return;
+                }
+                if compact {
+                    self.compact = true;
+                } else {
+                    self.out.push('\n');
+                }
+
+                for item in arr {
+                    self.indent(indent);
+                    self.out.push('-');
+                    self.compact = true;
+                    self.node(item, indent + 2);
+                }
+            },
+            scalar => {
+                let _ = writeln!(self.out, " {}", inline(scalar));
+            },
+        }
+    }
+}
+
+/// Rewrite documents using anchors and merge keys to remove duplicated entries.
+///
+/// The result is checked to merge into the same documents.
+pub fn factor(docs: &[Yaml], options: &FactorOptions) -> Result<String, FactorError> {
+    let mut planner = Planner {
+        min_shared: options.min_shared.max(1),
+        next_id: 0,
+        candidates: Vec::new(),
+        ancestors: HashSet::new(),
+        plans: HashMap::new(),
+        anchored: BTreeSet::new(),
+    };
+    for (idx, doc) in docs.iter().enumerate() {
+        planner.visit(idx, String::new(), doc);
+    }
+
+    let names = anchor_names(&planner, options);
+    let mut writer = Writer {
+        plans: &planner.plans,
+        names: &names,
+        next_id: 0,
+        compact: false,
+        out: String::new(),
+    };
+    for doc in docs {
+        writer.out.push_str("---");
+        writer.node(doc, 0);
+    }
+    let out = writer.out;
+
+    if yaml_merge_keys::load_merged(&out)? != docs {
+        return Err(FactorError::RoundTrip);
+    }
+
+    Ok(out)
+}
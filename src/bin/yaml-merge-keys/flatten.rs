@@ -0,0 +1,49 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use yaml_merge_keys::MERGE_KEY;
+use yaml_rust::{EmitError, Yaml, YamlEmitter};
+
+/// Whether a mapping key is a merge key.
+pub fn is_merge_key(key: &Yaml) -> bool {
+    matches!(key, Yaml::String(s) if s == MERGE_KEY)
+}
+
+/// Emit YAML documents as a stream.
+///
+/// Each document starts with a `---` separator.
+pub fn emit(docs: &[Yaml]) -> Result<String, EmitError> {
+    let mut out = String::new();
+    for doc in docs {
+        YamlEmitter::new(&mut out).dump(doc)?;
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Remove the document start marker the emitter puts before a value.
+pub fn strip_document_start(out: &str) -> &str {
+    out.strip_prefix("---\n")
+        .or_else(|| out.strip_prefix("--- "))
+        .unwrap_or(out)
+}
+
+/// Count the merge keys in a document.
+///
+/// Aliases are not followed, so each merge key in the source is counted once.
+pub fn count_merge_keys(doc: &Yaml) -> usize {
+    match doc {
+        Yaml::Hash(hash) => {
+            hash.iter()
+                .map(|(key, value)| {
+                    usize::from(is_merge_key(key)) + count_merge_keys(key) + count_merge_keys(value)
+                })
+                .sum()
+        },
+        Yaml::Array(arr) => arr.iter().map(count_merge_keys).sum(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,114 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Extract values from merged documents by path.
+//!
+//! See the `path` module for the syntax of paths.
+
+use std::slice;
+
+use clap::ValueEnum;
+use thiserror::Error;
+use yaml_rust::Yaml;
+
+use crate::flatten;
+use crate::json::{self, JsonError};
+use crate::path::{self, PathError};
+
+/// Errors which may occur when getting a value.
+#[derive(Debug, Error)]
+pub enum GetError {
+    /// The requested document does not exist.
+    #[error("there is no document {}", _0)]
+    NoDocument(usize),
+    /// The path does not exist in the document.
+    #[error("`{}` not found", _0)]
+    NotFound(String),
+    /// The path continues into a scalar.
+    #[error("`{}` is not a mapping or sequence", _0)]
+    NotCollection(String),
+    /// A collection was requested as a scalar.
+    #[error("`{}` is not a scalar; use `--output yaml` or `--output json`", _0)]
+    NotScalar(String),
+    /// The path could not be parsed.
+    #[error("{}", _0)]
+    Path(#[from] PathError),
+    /// The value could not be converted to JSON.
+    #[error("{}", _0)]
+    Json(#[from] JsonError),
+}
+
+impl GetError {
+    /// Whether the error means that the value does not exist.
+    pub fn is_missing(&self) -> bool {
+        matches!(
+            self,
+            GetError::NoDocument(_) | GetError::NotFound(_) | GetError::NotCollection(_),
+        )
+    }
+}
+
+/// Output formats for values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GetOutput {
+    /// The plain value of a scalar (strings are not quoted).
+    Scalar,
+    /// The value as YAML.
+    Yaml,
+    /// The value as pretty-printed JSON.
+    Json,
+}
+
+/// Find the value for a path segment within a node.
+pub fn lookup<'a>(doc: &'a Yaml, segment: &str) -> Option<&'a Yaml> {
+    match doc {
+        Yaml::Hash(hash) => {
+            hash.get(&Yaml::from_str(segment))
+                .or_else(|| hash.get(&Yaml::String(segment.into())))
+        },
+        Yaml::Array(arr) => segment.parse().ok().and_then(|idx: usize| arr.get(idx)),
+        _ => None,
+    }
+}
+
+/// Find the value at a path within a document.
+pub fn get<'a>(doc: &'a Yaml, path: &str) -> Result<&'a Yaml, GetError> {
+    let segments = path::parse(path)?;
+    let mut value = doc;
+    for (idx, segment) in segments.iter().enumerate() {
+        if !matches!(value, Yaml::Hash(_) | Yaml::Array(_)) {
+            return Err(GetError::NotCollection(path::format(&segments[..idx])));
+        }
+
+        value = lookup(value, segment)
+            .ok_or_else(|| GetError::NotFound(path::format(&segments[..=idx])))?;
+    }
+    Ok(value)
+}
+
+/// Format a value for output.
+pub fn format_value(value: &Yaml, path: &str, output: GetOutput) -> Result<String, GetError> {
+    Ok(match output {
+        GetOutput::Scalar => {
+            match value {
+                Yaml::String(s) | Yaml::Real(s) => format!("{}\n", s),
+                Yaml::Integer(i) => format!("{}\n", i),
+                Yaml::Boolean(b) => format!("{}\n", b),
+                Yaml::Null | Yaml::BadValue => "null\n".into(),
+                Yaml::Array(_) | Yaml::Hash(_) | Yaml::Alias(_) => {
+                    let path = if path.is_empty() { "." } else { path };
+                    return Err(GetError::NotScalar(path.into()));
+                },
+            }
+        },
+        GetOutput::Yaml => {
+            let out =
+                flatten::emit(slice::from_ref(value)).expect("merged documents may be emitted");
+            flatten::strip_document_start(&out).into()
+        },
+        GetOutput::Json => json::emit_json(slice::from_ref(value))?,
+    })
+}
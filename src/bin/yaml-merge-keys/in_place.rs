@@ -0,0 +1,92 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::ffi::OsString;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Whether a path names a YAML file.
+pub fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("yaml") | Some("yml"),
+    )
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            walk(&path, files)?;
+        } else if is_yaml(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Expand directories into the YAML files (`.yaml` and `.yml`) beneath them.
+///
+/// Files within a directory are sorted by path. Files given directly are kept whatever their
+/// extension.
+pub fn expand_paths(paths: Vec<PathBuf>) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(&path, &mut files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// A path with a suffix appended to its file name.
+fn with_suffix(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replace the contents of a file atomically.
+///
+/// The new contents are written to a temporary file in the same directory which is then renamed
+/// over the file so that readers see either the old or the new contents. The permissions of the
+/// file are kept. With a backup suffix, the old contents are first copied to a file with the
+/// suffix appended to its name.
+pub fn write_atomic(path: &Path, contents: &str, backup: Option<&str>) -> io::Result<()> {
+    let permissions = fs::metadata(path)?.permissions();
+    let tmp = with_suffix(path, ".", &format!(".{}.tmp", process::id()));
+
+    let res = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()?;
+            fs::set_permissions(&tmp, permissions)?;
+            if let Some(suffix) = backup {
+                fs::copy(path, with_suffix(path, "", suffix))?;
+            }
+            fs::rename(&tmp, path)
+        });
+
+    if res.is_err() {
+        // Do not leave the temporary file behind; the original error is more useful.
+        let _ = fs::remove_file(&tmp);
+    }
+
+    res
+}
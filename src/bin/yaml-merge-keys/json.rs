@@ -0,0 +1,117 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Conversion of YAML documents to JSON.
+//!
+//! - Mapping keys are converted to strings: integers, booleans, and null use their YAML spelling
+//!   (`1`, `true`, `null`) and floats are kept as written. Sequence and mapping keys are errors as
+//!   are keys which collide after conversion (e.g., `1` and `"1"`).
+//! - Integers are kept exactly. `yaml-rust` loads integers which do not fit in an `i64` as reals;
+//!   those up to `u64::MAX` become JSON integers and others are errors rather than being rounded
+//!   to floats.
+//! - `.inf`, `-.inf`, and `.nan` are not representable in JSON and become the strings `".inf"`,
+//!   `"-.inf"`, and `".nan"`.
+//! - Tags are not available from `yaml-rust`; values are converted by their resolved type and
+//!   scalars with custom tags are strings.
+
+use serde_json::{Map, Number, Value};
+use thiserror::Error;
+use yaml_rust::Yaml;
+
+/// Errors which may occur when converting YAML to JSON.
+#[derive(Debug, Error)]
+pub enum JsonError {
+    /// A mapping key is a sequence or mapping.
+    #[error("mapping keys must be scalars for JSON output")]
+    ComplexKey,
+    /// An integer does not fit in 64 bits.
+    #[error("integer `{}` is out of range for JSON output", _0)]
+    IntegerRange(String),
+    /// Two mapping keys are the same string in JSON.
+    #[error("duplicate key `{}` after converting keys to strings", _0)]
+    DuplicateKey(String),
+    /// A document could not be serialized.
+    #[error("failed to serialize JSON: {}", _0)]
+    Serialize(#[from] serde_json::Error),
+}
+
+fn convert_key(key: &Yaml) -> Result<String, JsonError> {
+    Ok(match key {
+        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+        Yaml::Integer(i) => i.to_string(),
+        Yaml::Boolean(b) => b.to_string(),
+        Yaml::Null | Yaml::BadValue | Yaml::Alias(_) => "null".into(),
+        Yaml::Array(_) | Yaml::Hash(_) => return Err(JsonError::ComplexKey),
+    })
+}
+
+fn convert_real(yaml: &Yaml, real: &str) -> Result<Value, JsonError> {
+    let unsigned = real.strip_prefix('+').unwrap_or(real);
+    let digits = unsigned.strip_prefix('-').unwrap_or(unsigned);
+    if digits.bytes().all(|b| b.is_ascii_digit()) {
+        return unsigned
+            .parse::<u64>()
+            .map(|u| Value::Number(u.into()))
+            .map_err(|_| JsonError::IntegerRange(real.into()));
+    }
+
+    Ok(match yaml.as_f64() {
+        Some(f) if f.is_nan() => Value::String(".nan".into()),
+        Some(f) if f.is_infinite() && f > 0. => Value::String(".inf".into()),
+        Some(f) if f.is_infinite() => Value::String("-.inf".into()),
+        Some(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        None => Value::String(real.into()),
+    })
+}
+
+/// Convert a YAML document to a JSON value.
+pub fn to_json(yaml: &Yaml) -> Result<Value, JsonError> {
+    Ok(match yaml {
+        Yaml::Real(real) => convert_real(yaml, real)?,
+        Yaml::Integer(i) => Value::Number((*i).into()),
+        Yaml::String(s) => Value::String(s.clone()),
+        Yaml::Boolean(b) => Value::Bool(*b),
+        Yaml::Array(arr) => Value::Array(arr.iter().map(to_json).collect::<Result<_, _>>()?),
+        Yaml::Hash(hash) => {
+            let mut map = Map::new();
+            for (key, value) in hash {
+                let key = convert_key(key)?;
+                if map.contains_key(&key) {
+                    return Err(JsonError::DuplicateKey(key));
+                }
+                map.insert(key, to_json(value)?);
+            }
+            Value::Object(map)
+        },
+        // Aliases have been expanded in merged documents; invalid values are emitted as null in
+        // YAML output as well.
+        Yaml::Null | Yaml::BadValue | Yaml::Alias(_) => Value::Null,
+    })
+}
+
+/// Emit YAML documents as pretty-printed JSON.
+///
+/// Each document is emitted as a separate JSON value.
+pub fn emit_json(docs: &[Yaml]) -> Result<String, JsonError> {
+    let mut out = String::new();
+    for doc in docs {
+        out.push_str(&serde_json::to_string_pretty(&to_json(doc)?)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Emit YAML documents as newline-delimited JSON.
+///
+/// Each document is emitted on its own line.
+pub fn emit_ndjson(docs: &[Yaml]) -> Result<String, JsonError> {
+    let mut out = String::new();
+    for doc in docs {
+        out.push_str(&serde_json::to_string(&to_json(doc)?)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
@@ -0,0 +1,468 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Flatten YAML merge keys in documents.
+//!
+//! Documents may be printed as JSON with `--output json` or `--output ndjson`; see the `json`
+//! module for how YAML values are converted.
+//!
+//! With `--in-place`, files (or the YAML files in directories) are replaced by their flattened
+//! form and the number of merge keys resolved in each file is printed.
+//!
+//! The `diff` subcommand shows the keys added and overridden by merge keys or compares two merged
+//! files.
+//!
+//! The `factor` subcommand does the reverse of flattening: it introduces anchors and merge keys
+//! for duplicated entries.
+//!
+//! The `get` subcommand prints the value at a path in the merged document.
+//!
+//! The `watch` subcommand re-checks files whenever they change.
+//!
+//! The `explain` subcommand prints a value and where it came from.
+//!
+//! The `check` subcommand only validates merge keys and reports `file:line:col: message`
+//! diagnostics. It exits with 1 for merge key errors, 3 for YAML syntax errors, and 4 for
+//! unreadable files.
+
+// The dependencies of the `cli` feature need a newer compiler than the library, so the binary is
+// not limited to the crate's MSRV.
+#![allow(clippy::incompatible_msrv)]
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use yaml_rust::{Yaml, YamlLoader};
+
+mod check;
+mod diff;
+mod explain;
+mod factor;
+mod flatten;
+mod get;
+mod in_place;
+mod json;
+mod path;
+mod watch;
+
+use check::Status;
+
+/// Resolve YAML merge keys and print the flattened documents.
+#[derive(Debug, Parser)]
+#[command(version, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The format to print the documents in.
+    #[arg(long, short, value_enum, default_value = "yaml")]
+    output: Output,
+    /// Replace each file with its flattened form.
+    ///
+    /// Directories are searched for `.yaml` and `.yml` files. Files without merge keys are left
+    /// untouched.
+    #[arg(long, short, conflicts_with = "output")]
+    in_place: bool,
+    /// Keep a copy of each rewritten file with the suffix appended to its name.
+    #[arg(long, value_name = "SUFFIX", requires = "in_place")]
+    backup: Option<String>,
+    /// Files to read (standard input is read if none are given or for `-`).
+    files: Vec<PathBuf>,
+}
+
+/// Output formats for flattened documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Output {
+    /// A YAML stream with each document starting with `---`.
+    Yaml,
+    /// Pretty-printed JSON with one value per document.
+    Json,
+    /// JSON with one document per line.
+    Ndjson,
+}
+
+impl Output {
+    fn emit(self, docs: &[Yaml]) -> Result<String, String> {
+        match self {
+            Output::Yaml => flatten::emit(docs).map_err(|err| format!("{:?}", err)),
+            Output::Json => json::emit_json(docs).map_err(|err| err.to_string()),
+            Output::Ndjson => json::emit_ndjson(docs).map_err(|err| err.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Check that merge keys may be resolved without printing the documents.
+    Check {
+        /// Files to check (standard input is read if none are given or for `-`).
+        files: Vec<PathBuf>,
+    },
+    /// Show the differences made by merge keys or between two files.
+    ///
+    /// With one file, each mapping with merge keys is listed with the keys added (`+`) and
+    /// overridden (`~`) by merging. With two files, the merged documents are compared ignoring
+    /// key order. Exits with 0 without differences, 1 with differences, and 2 on errors.
+    Diff {
+        /// The file to read (standard input is read for `-`).
+        file: PathBuf,
+        /// The file to compare against.
+        other: Option<PathBuf>,
+    },
+    /// Rewrite a file using anchors and merge keys to remove duplication.
+    ///
+    /// Mappings which share entries with an earlier mapping merge it instead and mappings equal
+    /// to an earlier mapping become aliases. The output is checked to merge into the input.
+    Factor {
+        /// The file to read (standard input is read for `-`).
+        file: PathBuf,
+        /// The minimum number of entries a merge must provide.
+        #[arg(long, default_value_t = 2)]
+        min_shared: usize,
+        /// How to name anchors.
+        #[arg(long, value_enum, default_value = "path")]
+        anchor_names: factor::AnchorNames,
+        /// A prefix for anchor names.
+        #[arg(long, default_value = "")]
+        anchor_prefix: String,
+    },
+    /// Print the value at a path in the merged document.
+    ///
+    /// The value is named by a dotted path of mapping keys and sequence indices (e.g.,
+    /// `environments.prod.url`). Exits with 0 when the value exists, 1 when it does not, and 2
+    /// on errors.
+    #[command(visible_alias = "query")]
+    Get {
+        /// The file to read (standard input is read for `-`).
+        file: PathBuf,
+        /// The path to the value.
+        path: String,
+        /// The format to print the value in.
+        #[arg(long, short, value_enum, default_value = "scalar")]
+        output: get::GetOutput,
+        /// The index of the document to look in.
+        #[arg(long, short, default_value_t = 0)]
+        document: usize,
+    },
+    /// Re-check YAML files whenever they change.
+    ///
+    /// Every YAML file is checked at the start. Afterwards, each changed file is reported with
+    /// diagnostics for errors or its flattened documents.
+    Watch {
+        /// Files and directories to watch.
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Only report whether changed files are valid rather than printing them.
+        #[arg(long)]
+        check: bool,
+    },
+    /// Print a value and where it came from.
+    ///
+    /// The value is named by a dotted path of mapping keys and sequence indices (e.g.,
+    /// `jobs.build.image`). Each step of the path shows whether its entry is local or inherited
+    /// through merge keys and which other entries it shadows. Positions are `line:column`.
+    Explain {
+        /// The file to read (standard input is read for `-`).
+        file: PathBuf,
+        /// The path to the value.
+        path: String,
+        /// The index of the document to look in.
+        #[arg(long, short, default_value_t = 0)]
+        document: usize,
+    },
+}
+
+/// A source of YAML documents.
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    fn from_args(files: Vec<PathBuf>) -> Vec<Self> {
+        if files.is_empty() {
+            return vec![Input::Stdin];
+        }
+
+        files
+            .into_iter()
+            .map(|path| {
+                if path.as_os_str() == "-" {
+                    Input::Stdin
+                } else {
+                    Input::File(path)
+                }
+            })
+            .collect()
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Input::Stdin => "<stdin>".into(),
+            Input::File(path) => path.display().to_string(),
+        }
+    }
+
+    fn read(&self) -> io::Result<String> {
+        match self {
+            Input::Stdin => {
+                let mut source = String::new();
+                io::stdin().read_to_string(&mut source)?;
+                Ok(source)
+            },
+            Input::File(path) => fs::read_to_string(path),
+        }
+    }
+}
+
+fn run(files: Vec<PathBuf>, output: Output) -> Result<(), String> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for input in Input::from_args(files) {
+        let name = input.name();
+        let source = input.read().map_err(|err| format!("{}: {}", name, err))?;
+        let docs =
+            yaml_merge_keys::load_merged(&source).map_err(|err| check::diagnostic(&name, &err))?;
+        let out = output
+            .emit(&docs)
+            .map_err(|err| format!("{}: {}", name, err))?;
+        stdout
+            .write_all(out.as_bytes())
+            .map_err(|err| format!("<stdout>: {}", err))?;
+    }
+
+    Ok(())
+}
+
+fn run_in_place(files: Vec<PathBuf>, backup: Option<&str>) -> Result<(), String> {
+    if files.is_empty() || files.iter().any(|path| path.as_os_str() == "-") {
+        return Err("--in-place requires files to rewrite".into());
+    }
+    let files = in_place::expand_paths(files).map_err(|err| err.to_string())?;
+
+    // Flatten every file before writing any of them so that an error leaves them all untouched.
+    let flattened = files
+        .into_iter()
+        .map(|path| {
+            let name = path.display().to_string();
+            let source = fs::read_to_string(&path).map_err(|err| format!("{}: {}", name, err))?;
+            let docs = yaml_merge_keys::load_merged(&source)
+                .map_err(|err| check::diagnostic(&name, &err))?;
+            let (raw, _) = yaml_merge_keys::load_with_anchors(&source)
+                .map_err(|err| format!("{}: {}", name, err))?;
+            let merges = raw.iter().map(flatten::count_merge_keys).sum::<usize>();
+            let out = flatten::emit(&docs).map_err(|err| format!("{}: {:?}", name, err))?;
+            Ok((path, name, merges, out))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut total = 0;
+    for (path, name, merges, out) in &flattened {
+        if *merges == 0 {
+            println!("{}: no merge keys (unchanged)", name);
+            continue;
+        }
+
+        in_place::write_atomic(path, out, backup).map_err(|err| format!("{}: {}", name, err))?;
+        println!("{}: {} merge key(s) resolved", name, merges);
+        total += merges;
+    }
+    println!(
+        "{} merge key(s) resolved in {} file(s)",
+        total,
+        flattened.len(),
+    );
+
+    Ok(())
+}
+
+fn run_explain(file: PathBuf, path: &str, document: usize) -> Result<(), String> {
+    let input = Input::from_args(vec![file]).remove(0);
+    let name = input.name();
+    let source = input.read().map_err(|err| format!("{}: {}", name, err))?;
+    let explanation = explain::explain(&source, path, document).map_err(|err| {
+        match err {
+            explain::ExplainError::Load(err) => check::diagnostic(&name, &err),
+            err => format!("{}: {}", name, err),
+        }
+    })?;
+    print!("{}", explanation);
+
+    Ok(())
+}
+
+/// Read a file and handle its merge keys.
+fn read_merged(input: &Input) -> Result<(String, Vec<Yaml>), String> {
+    let name = input.name();
+    let source = input.read().map_err(|err| format!("{}: {}", name, err))?;
+    let docs =
+        yaml_merge_keys::load_merged(&source).map_err(|err| check::diagnostic(&name, &err))?;
+    Ok((source, docs))
+}
+
+fn run_diff(file: PathBuf, other: Option<PathBuf>) -> Result<String, String> {
+    let input = Input::from_args(vec![file]).remove(0);
+    let (source, merged) = read_merged(&input)?;
+
+    if let Some(other) = other {
+        let other = Input::from_args(vec![other]).remove(0);
+        let (_, other_merged) = read_merged(&other)?;
+        Ok(diff::diff_documents(&merged, &other_merged))
+    } else {
+        let name = input.name();
+        let raw = YamlLoader::load_from_str(&source).map_err(|err| format!("{}: {}", name, err))?;
+        raw.iter()
+            .zip(&merged)
+            .map(|(raw, merged)| diff::diff_merges(raw, merged))
+            .collect::<Result<String, _>>()
+            .map_err(|err| format!("{}: {}", name, err))
+    }
+}
+
+fn run_factor(file: PathBuf, options: &factor::FactorOptions) -> Result<(), String> {
+    let input = Input::from_args(vec![file]).remove(0);
+    let (_, docs) = read_merged(&input)?;
+    let out = factor::factor(&docs, options).map_err(|err| format!("{}: {}", input.name(), err))?;
+    print!("{}", out);
+
+    Ok(())
+}
+
+fn run_get(
+    file: PathBuf,
+    path: &str,
+    output: get::GetOutput,
+    document: usize,
+) -> Result<String, (bool, String)> {
+    let input = Input::from_args(vec![file]).remove(0);
+    let name = input.name();
+    let (_, docs) = read_merged(&input).map_err(|msg| (false, msg))?;
+
+    docs.get(document)
+        .ok_or(get::GetError::NoDocument(document))
+        .and_then(|doc| get::get(doc, path))
+        .and_then(|value| get::format_value(value, path, output))
+        .map_err(|err| (err.is_missing(), format!("{}: {}", name, err)))
+}
+
+fn run_check(files: Vec<PathBuf>) -> Status {
+    Input::from_args(files)
+        .into_iter()
+        .map(|input| {
+            let name = input.name();
+            let res = input
+                .read()
+                .map_err(|err| (Status::ReadError, format!("{}: {}", name, err)))
+                .and_then(|source| {
+                    yaml_merge_keys::check(&source)
+                        .map_err(|err| (Status::from_error(&err), check::diagnostic(&name, &err)))
+                });
+
+            match res {
+                Ok(()) => Status::Valid,
+                Err((status, msg)) => {
+                    eprintln!("{}", msg);
+                    status
+                },
+            }
+        })
+        .fold(Status::Valid, Status::max)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Some(Command::Check {
+            files,
+        }) => ExitCode::from(run_check(files).exit_code()),
+        Some(Command::Get {
+            file,
+            path,
+            output,
+            document,
+        }) => {
+            match run_get(file, &path, output, document) {
+                Ok(value) => {
+                    print!("{}", value);
+                    ExitCode::SUCCESS
+                },
+                Err((missing, msg)) => {
+                    eprintln!("yaml-merge-keys: {}", msg);
+                    if missing {
+                        ExitCode::FAILURE
+                    } else {
+                        ExitCode::from(2)
+                    }
+                },
+            }
+        },
+        Some(Command::Diff {
+            file,
+            other,
+        }) => {
+            match run_diff(file, other) {
+                Ok(diff) => {
+                    print!("{}", diff);
+                    if diff.is_empty() {
+                        ExitCode::SUCCESS
+                    } else {
+                        ExitCode::FAILURE
+                    }
+                },
+                Err(msg) => {
+                    eprintln!("yaml-merge-keys: {}", msg);
+                    ExitCode::from(2)
+                },
+            }
+        },
+        command => {
+            let res = match command {
+                Some(Command::Explain {
+                    file,
+                    path,
+                    document,
+                }) => run_explain(file, &path, document),
+                Some(Command::Watch {
+                    paths,
+                    check,
+                }) => watch::watch(&paths, check),
+                Some(Command::Factor {
+                    file,
+                    min_shared,
+                    anchor_names,
+                    anchor_prefix,
+                }) => {
+                    let options = factor::FactorOptions {
+                        min_shared,
+                        anchor_names,
+                        anchor_prefix,
+                    };
+                    run_factor(file, &options)
+                },
+                _ if cli.in_place => run_in_place(cli.files, cli.backup.as_deref()),
+                _ => run(cli.files, cli.output),
+            };
+
+            match res {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(msg) => {
+                    eprintln!("yaml-merge-keys: {}", msg);
+                    ExitCode::FAILURE
+                },
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod test;
+#[cfg(test)]
+#[path = "../../test_dir.rs"]
+mod test_dir;
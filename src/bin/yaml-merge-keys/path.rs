@@ -0,0 +1,100 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Paths to values within documents.
+//!
+//! Paths are mapping keys and sequence indices separated by `.` (e.g.,
+//! `environments.prod.urls.0`). An empty path (or `.`) is the whole document. Keys which contain
+//! `.` may be quoted (`'example.com'.port`), with `''` for a quote within a quoted key, or have
+//! the character escaped with `\` (`example\.com.port`). Quoted keys may be empty.
+
+use std::borrow::Cow;
+use std::mem;
+
+use thiserror::Error;
+
+/// Errors which may occur when parsing a path.
+#[derive(Debug, Error)]
+pub enum PathError {
+    /// A quoted key is not closed.
+    #[error("unterminated quote in path `{}`", _0)]
+    UnterminatedQuote(String),
+    /// The path ends with an escape character.
+    #[error("path `{}` ends with `\\`", _0)]
+    TrailingEscape(String),
+}
+
+/// Split a path into its segments.
+pub fn parse(path: &str) -> Result<Vec<String>, PathError> {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    // Quoted segments are kept even if they are empty.
+    let mut quoted = false;
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                if quoted || !segment.is_empty() {
+                    segments.push(mem::take(&mut segment));
+                }
+                quoted = false;
+            },
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| PathError::TrailingEscape(path.into()))?;
+                segment.push(escaped);
+            },
+            '\'' => {
+                quoted = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') if chars.as_str().starts_with('\'') => {
+                            chars.next();
+                            segment.push('\'');
+                        },
+                        Some('\'') => break,
+                        Some(c) => segment.push(c),
+                        None => return Err(PathError::UnterminatedQuote(path.into())),
+                    }
+                }
+            },
+            c => segment.push(c),
+        }
+    }
+    if quoted || !segment.is_empty() {
+        segments.push(segment);
+    }
+
+    Ok(segments)
+}
+
+/// Quote a segment if it would not be parsed back as itself.
+pub fn quote(segment: &str) -> Cow<'_, str> {
+    if segment.is_empty() || segment.contains(['.', '\'', '\\']) {
+        format!("'{}'", segment.replace('\'', "''")).into()
+    } else {
+        segment.into()
+    }
+}
+
+/// Format path segments as a path.
+///
+/// The empty path is shown as `.`.
+pub fn format<S>(segments: &[S]) -> String
+where
+    S: AsRef<str>,
+{
+    if segments.is_empty() {
+        return ".".into();
+    }
+
+    segments
+        .iter()
+        .map(|segment| quote(segment.as_ref()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
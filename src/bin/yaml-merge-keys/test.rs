@@ -0,0 +1,901 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::slice;
+use std::time::Duration;
+
+use yaml_merge_keys::{check, load_merged, load_with_anchors, Error, ErrorKind};
+
+use notify::event::{AccessKind, CreateKind, DataChange, EventKind, ModifyKind, RemoveKind};
+use notify::Event;
+use yaml_rust::YamlLoader;
+
+use crate::check::{diagnostic, Status};
+use crate::diff::{diff_documents, diff_merges, inline};
+use crate::explain::{explain, ExplainError};
+use crate::factor::{factor, AnchorNames, FactorOptions};
+use crate::flatten::{count_merge_keys, emit};
+use crate::get::{format_value, get, GetError, GetOutput};
+use crate::in_place::{expand_paths, write_atomic};
+use crate::json::{emit_json, emit_ndjson, JsonError};
+use crate::path::{self, PathError};
+use crate::test_dir::TestDir;
+use crate::watch::{changed_paths, report, Notifications, Watched};
+
+fn flatten(source: &str) -> Result<String, Error> {
+    load_merged(source).map(|docs| emit(&docs).unwrap())
+}
+
+#[test]
+fn test_flatten() {
+    let raw = "\
+base: &base
+  a: 1
+dict:
+  <<: *base
+  b: 2
+";
+    let expected = "\
+---
+base:
+  a: 1
+dict:
+  b: 2
+  a: 1
+";
+
+    assert_eq!(flatten(raw).unwrap(), expected);
+}
+
+#[test]
+fn test_flatten_documents() {
+    let raw = "\
+a: &a {x: 1}
+b: {<<: *a}
+---
+- {<<: {y: 2}}
+";
+    let expected = "\
+---
+a:
+  x: 1
+b:
+  x: 1
+---
+- y: 2
+";
+
+    assert_eq!(flatten(raw).unwrap(), expected);
+}
+
+#[test]
+fn test_flatten_empty() {
+    assert_eq!(flatten("").unwrap(), "");
+}
+
+#[test]
+fn test_flatten_errors() {
+    let syntax = flatten("a: [").unwrap_err();
+    let merge = flatten("a: {<<: 1}").unwrap_err();
+
+    assert_eq!(syntax.kind(), ErrorKind::Syntax);
+    assert_eq!(merge.kind(), ErrorKind::InvalidMergeValue);
+    assert_eq!(
+        merge.to_string(),
+        "only mappings and arrays of mappings may be merged",
+    );
+}
+
+fn check_diagnostic(source: &str) -> (Status, String) {
+    let err = check(source).unwrap_err();
+    (Status::from_error(&err), diagnostic("config.yaml", &err))
+}
+
+#[test]
+fn test_check_merge_error() {
+    let raw = "\
+a:
+  b:
+    <<: [{c: 1}, 2]
+";
+
+    assert_eq!(
+        check_diagnostic(raw),
+        (
+            Status::MergeError,
+            "config.yaml:3:5: only mappings and arrays of mappings may be merged".into(),
+        ),
+    );
+}
+
+#[test]
+fn test_check_undefined_alias() {
+    let raw = "\
+a: &a
+  <<: *a
+";
+
+    assert_eq!(
+        check_diagnostic(raw),
+        (
+            Status::MergeError,
+            "config.yaml:2:7: undefined alias `*a`".into(),
+        ),
+    );
+}
+
+#[test]
+fn test_check_unknown_anchor() {
+    // Aliases to anchors which never appear are rejected by the parser.
+    let raw = "\
+a: &a
+  b: 1
+c:
+  <<: *typo
+";
+    let (status, msg) = check_diagnostic(raw);
+
+    assert_eq!(status, Status::ParseError);
+    assert!(msg.starts_with("config.yaml:4:7: "), "{}", msg);
+    assert!(msg.contains("unknown anchor"), "{}", msg);
+}
+
+#[test]
+fn test_check_syntax_error() {
+    let (status, msg) = check_diagnostic("a:\n  b: c: d\n");
+
+    assert_eq!(status, Status::ParseError);
+    assert!(msg.starts_with("config.yaml:2:7: "), "{}", msg);
+    assert!(!msg.ends_with("column 7"), "{}", msg);
+}
+
+#[test]
+fn test_check_status() {
+    assert_eq!(Status::Valid.exit_code(), 0);
+    assert_eq!(Status::MergeError.exit_code(), 1);
+    assert_eq!(Status::ParseError.exit_code(), 3);
+    assert_eq!(Status::ReadError.exit_code(), 4);
+    assert_eq!(
+        vec![Status::ParseError, Status::MergeError, Status::Valid]
+            .into_iter()
+            .fold(Status::Valid, Status::max),
+        Status::ParseError,
+    );
+}
+
+#[test]
+fn test_json() {
+    let raw = "\
+base: &base
+  a: 1
+  f: 1.5
+dict:
+  <<: *base
+  b: [true, ~, text]
+";
+    let expected = "\
+{
+  \"base\": {
+    \"a\": 1,
+    \"f\": 1.5
+  },
+  \"dict\": {
+    \"b\": [
+      true,
+      null,
+      \"text\"
+    ],
+    \"a\": 1,
+    \"f\": 1.5
+  }
+}
+";
+
+    assert_eq!(emit_json(&load_merged(raw).unwrap()).unwrap(), expected);
+}
+
+#[test]
+fn test_ndjson() {
+    let raw = "\
+a: {<<: {x: 1}}
+---
+- 1
+---
+";
+
+    assert_eq!(
+        emit_ndjson(&load_merged(raw).unwrap()).unwrap(),
+        "{\"a\":{\"x\":1}}\n[1]\nnull\n",
+    );
+}
+
+#[test]
+fn test_json_keys() {
+    let raw = "\
+1: int
+1.50: float
+true: bool
+~: none
+";
+
+    assert_eq!(
+        emit_ndjson(&load_merged(raw).unwrap()).unwrap(),
+        "{\"1\":\"int\",\"1.50\":\"float\",\"true\":\"bool\",\"null\":\"none\"}\n",
+    );
+}
+
+#[test]
+fn test_json_non_finite() {
+    let raw = "[.inf, -.Inf, .NAN, !custom 3]";
+
+    assert_eq!(
+        emit_ndjson(&load_merged(raw).unwrap()).unwrap(),
+        "[\".inf\",\"-.inf\",\".nan\",\"3\"]\n",
+    );
+}
+
+#[test]
+fn test_json_big_integers() {
+    let raw = "[18446744073709551615, +9223372036854775808, -9223372036854775808, 1e3]";
+
+    assert_eq!(
+        emit_ndjson(&load_merged(raw).unwrap()).unwrap(),
+        "[18446744073709551615,9223372036854775808,-9223372036854775808,1000.0]\n",
+    );
+
+    let err = emit_json(&load_merged("big: 18446744073709551616").unwrap()).unwrap_err();
+    assert!(matches!(err, JsonError::IntegerRange(ref int) if int == "18446744073709551616"));
+    assert_eq!(
+        err.to_string(),
+        "integer `18446744073709551616` is out of range for JSON output",
+    );
+
+    let err = emit_json(&load_merged("[-9223372036854775809]").unwrap()).unwrap_err();
+    assert!(matches!(err, JsonError::IntegerRange(_)));
+}
+
+#[test]
+fn test_json_key_errors() {
+    let complex = emit_json(&load_merged("? [1]\n: 2\n").unwrap()).unwrap_err();
+    let duplicate = emit_json(&load_merged("1: a\n'1': b\n").unwrap()).unwrap_err();
+
+    assert!(matches!(complex, JsonError::ComplexKey));
+    assert!(matches!(duplicate, JsonError::DuplicateKey(ref key) if key == "1"));
+    assert_eq!(
+        duplicate.to_string(),
+        "duplicate key `1` after converting keys to strings",
+    );
+}
+
+#[test]
+fn test_count_merge_keys() {
+    let raw = "\
+base: &base
+  <<: {a: 1}
+dict:
+  <<: [*base, {b: 2}]
+list:
+  - <<: *base
+---
+a: 1
+";
+    let (docs, _) = load_with_anchors(raw).unwrap();
+    let counts = docs.iter().map(count_merge_keys).collect::<Vec<_>>();
+
+    assert_eq!(counts, [3, 0]);
+}
+
+#[test]
+fn test_expand_paths() {
+    let test_dir = TestDir::new("expand");
+    for file in &["b.yaml", "a.yml", "notes.txt", "sub/c.yaml"] {
+        test_dir.write(file, "");
+    }
+    let dir = test_dir.path();
+    let extra = dir.join("notes.txt");
+
+    let files = expand_paths(vec![dir.into(), extra.clone()]).unwrap();
+
+    assert_eq!(
+        files,
+        [
+            dir.join("a.yml"),
+            dir.join("b.yaml"),
+            dir.join("sub/c.yaml"),
+            extra,
+        ],
+    );
+}
+
+#[test]
+fn test_write_atomic() {
+    let test_dir = TestDir::new("write");
+    let dir = test_dir.path();
+    let path = test_dir.write("config.yaml", "old\n");
+
+    write_atomic(&path, "new\n", None).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+
+    write_atomic(&path, "newer\n", Some(".orig")).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "newer\n");
+    assert_eq!(
+        fs::read_to_string(dir.join("config.yaml.orig")).unwrap(),
+        "new\n",
+    );
+
+    let mut entries = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    entries.sort();
+    assert_eq!(entries, ["config.yaml", "config.yaml.orig"]);
+}
+
+#[test]
+fn test_write_atomic_missing() {
+    let dir = TestDir::new("missing");
+
+    write_atomic(&dir.path().join("missing.yaml"), "new\n", None).unwrap_err();
+
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+const EXPLAIN: &str = "\
+.base: &base
+  image: alpine
+  tags: [docker]
+.ruby: &ruby
+  <<: *base
+  image: ruby
+jobs:
+  build:
+    <<: [*ruby, {image: inline, retry: 2}]
+  test:
+    <<: *ruby
+    image: local
+";
+
+#[test]
+fn test_explain_inherited() {
+    let expected = "\
+jobs.build.image: ruby
+jobs
+  local at 7:1
+jobs.build
+  local at 8:3
+jobs.build.image
+  inherited from 6:3 through `<<: *ruby` at 9:10
+  shadows inherited from 2:3 through `<<: *ruby` at 9:10 then `<<: *base` at 5:7
+  shadows inherited from 9:18 through `<<` at 9:5
+";
+
+    assert_eq!(explain(EXPLAIN, "jobs.build.image", 0).unwrap(), expected);
+}
+
+#[test]
+fn test_explain_local() {
+    let expected = "\
+jobs.test.image: local
+jobs
+  local at 7:1
+jobs.test
+  local at 10:3
+jobs.test.image
+  local at 12:5
+  shadows inherited from 6:3 through `<<: *ruby` at 11:9
+  shadows inherited from 2:3 through `<<: *ruby` at 11:9 then `<<: *base` at 5:7
+";
+
+    assert_eq!(explain(EXPLAIN, "jobs.test.image", 0).unwrap(), expected);
+}
+
+#[test]
+fn test_explain_sequence_and_collection() {
+    let expected = "\
+jobs.build.tags:
+  - docker
+jobs
+  local at 7:1
+jobs.build
+  local at 8:3
+jobs.build.tags
+  inherited from 3:3 through `<<: *ruby` at 9:10 then `<<: *base` at 5:7
+";
+
+    assert_eq!(explain(EXPLAIN, "jobs.build.tags", 0).unwrap(), expected);
+    assert!(explain(EXPLAIN, "jobs.build.tags.0", 0)
+        .unwrap()
+        .starts_with("jobs.build.tags.0: docker\n"));
+}
+
+#[test]
+fn test_explain_quoted_path() {
+    let expected = "\
+'.ruby'.image: ruby
+'.ruby'
+  local at 4:1
+'.ruby'.image
+  local at 6:3
+  shadows inherited from 2:3 through `<<: *base` at 5:7
+";
+
+    assert_eq!(explain(EXPLAIN, "'.ruby'.image", 0).unwrap(), expected);
+    assert_eq!(explain(EXPLAIN, "\\.ruby.image", 0).unwrap(), expected);
+}
+
+#[test]
+fn test_explain_errors() {
+    let not_found = explain(EXPLAIN, "jobs.deploy", 0).unwrap_err();
+    let scalar = explain(EXPLAIN, "jobs.test.image.name", 0).unwrap_err();
+    let document = explain(EXPLAIN, "jobs", 1).unwrap_err();
+    let merge = explain("a: {<<: 1}", "a", 0).unwrap_err();
+    let path = explain(EXPLAIN, "'jobs", 0).unwrap_err();
+
+    assert_eq!(not_found.to_string(), "`jobs.deploy` not found");
+    assert_eq!(
+        scalar.to_string(),
+        "`jobs.test.image` is not a mapping or sequence",
+    );
+    assert!(matches!(document, ExplainError::NoDocument(1)));
+    assert!(matches!(merge, ExplainError::Load(_)));
+    assert!(matches!(path, ExplainError::Path(_)));
+}
+
+#[test]
+fn test_inline() {
+    let raw = "{a: [1, 'two words', ~], b: {c: true}, '1': x}";
+    let doc = YamlLoader::load_from_str(raw).unwrap().remove(0);
+
+    assert_eq!(
+        inline(&doc),
+        "{a: [1, two words, ~], b: {c: true}, \"1\": x}",
+    );
+}
+
+#[test]
+fn test_diff_merges() {
+    let raw = "\
+base: &base
+  a: 1
+  b: 2
+list:
+  - <<: *base
+    b: 3
+  - c: 4
+    nested:
+      <<: [*base, {d: 5}]
+      a: 1
+";
+    let expected = "\
+list.0
+  + a: 1
+  ~ b: 3 (overrides 2)
+list.1.nested
+  + b: 2
+  + d: 5
+  ~ a: 1 (overrides 1)
+";
+
+    let raw_docs = YamlLoader::load_from_str(raw).unwrap();
+    let merged = load_merged(raw).unwrap();
+
+    assert_eq!(diff_merges(&raw_docs[0], &merged[0]).unwrap(), expected);
+}
+
+#[test]
+fn test_diff_merges_none() {
+    let raw = "a: {b: [1, {c: 2}]}";
+    let raw_docs = YamlLoader::load_from_str(raw).unwrap();
+    let merged = load_merged(raw).unwrap();
+
+    assert_eq!(diff_merges(&raw_docs[0], &merged[0]).unwrap(), "");
+}
+
+#[test]
+fn test_diff_documents() {
+    let old = "\
+base: &base
+  image: alpine
+  tags: [a, b]
+job:
+  <<: *base
+  script: make
+";
+    let new = "\
+job:
+  script: make all
+  image: debian
+  tags: [a]
+  retry: 2
+base:
+  tags: [a]
+  image: alpine
+";
+    let expected = "\
+- base.tags.1: b
+~ job.script: make -> make all
+~ job.image: alpine -> debian
+- job.tags.1: b
++ job.retry: 2
+";
+
+    let old = load_merged(old).unwrap();
+    let new = load_merged(new).unwrap();
+
+    assert_eq!(diff_documents(&old, &old), "");
+    assert_eq!(diff_documents(&old, &new), expected);
+}
+
+#[test]
+fn test_diff_documents_streams() {
+    let old = load_merged("a: 1\n---\nb: 2\n").unwrap();
+    let new = load_merged("a: 1\n---\nb: {<<: {c: 3}}\n---\n[1]\n").unwrap();
+    let expected = "\
+--- document 1
+~ b: 2 -> {c: 3}
+--- document 2
++ .: [1]
+";
+
+    assert_eq!(diff_documents(&old, &new), expected);
+}
+
+#[test]
+fn test_diff_documents_quoted_keys() {
+    let old = load_merged("example.com: {port: 80}\n").unwrap();
+    let new = load_merged("example.com: {port: 8080}\n").unwrap();
+
+    assert_eq!(
+        diff_documents(&old, &new),
+        "~ 'example.com'.port: 80 -> 8080\n",
+    );
+}
+
+fn factor_options(min_shared: usize, anchor_names: AnchorNames) -> FactorOptions {
+    FactorOptions {
+        min_shared,
+        anchor_names,
+        anchor_prefix: String::new(),
+    }
+}
+
+const FACTOR: &str = "\
+defaults:
+  retries: 3
+  timeout: 30
+jobs:
+  - name: build
+    retries: 3
+    timeout: 30
+  - name: test
+    retries: 5
+    timeout: 30
+  - name: lint
+    retries: 3
+    timeout: 30
+  - {retries: 3, timeout: 30}
+";
+
+#[test]
+fn test_factor() {
+    let expected = "\
+---
+defaults: &defaults
+  retries: 3
+  timeout: 30
+jobs:
+  - <<: *defaults
+    name: build
+  - name: test
+    retries: 5
+    timeout: 30
+  - <<: *defaults
+    name: lint
+  - *defaults
+";
+
+    let docs = load_merged(FACTOR).unwrap();
+    let factored = factor(&docs, &factor_options(2, AnchorNames::Path)).unwrap();
+
+    assert_eq!(factored, expected);
+    assert_eq!(load_merged(&factored).unwrap(), docs);
+}
+
+#[test]
+fn test_factor_options() {
+    let docs = load_merged(FACTOR).unwrap();
+    let numbered = factor(&docs, &factor_options(1, AnchorNames::Numbered)).unwrap();
+    let unchanged = factor(&docs, &factor_options(3, AnchorNames::Path)).unwrap();
+
+    assert!(numbered.starts_with("---\ndefaults: &anchor1\n"));
+    assert!(numbered.contains("  - <<: *anchor1\n    name: test\n    retries: 5\n"));
+    assert_eq!(load_merged(&numbered).unwrap(), docs);
+    assert_eq!(unchanged, emit(&docs).unwrap());
+}
+
+#[test]
+fn test_factor_key_order() {
+    // Merged entries come after local entries, so shared entries at the start of a mapping may
+    // not be factored out.
+    let raw = "\
+- {a: 1, b: 2}
+- {a: 1, b: 2, c: 3}
+- {c: 3, a: 1, b: 2}
+";
+    let expected = "\
+---
+- &root_0
+  a: 1
+  b: 2
+- a: 1
+  b: 2
+  c: 3
+- <<: *root_0
+  c: 3
+";
+
+    let docs = load_merged(raw).unwrap();
+    let factored = factor(&docs, &factor_options(2, AnchorNames::Path)).unwrap();
+
+    assert_eq!(factored, expected);
+    assert_eq!(load_merged(&factored).unwrap(), docs);
+}
+
+const GET: &str = "\
+defaults: &defaults
+  url: https://example.com
+  replicas: 2
+environments:
+  prod:
+    <<: *defaults
+    url: https://prod.example.com
+    hosts: [a, b]
+  '1': {enabled: true, ratio: 0.50, owner: ~}
+";
+
+fn get_value(path: &str, output: GetOutput) -> Result<String, GetError> {
+    let docs = load_merged(GET).unwrap();
+    get(&docs[0], path).and_then(|value| format_value(value, path, output))
+}
+
+#[test]
+fn test_get_scalar() {
+    let cases = [
+        ("environments.prod.url", "https://prod.example.com\n"),
+        ("environments.prod.replicas", "2\n"),
+        ("environments.prod.hosts.1", "b\n"),
+        ("environments.1.enabled", "true\n"),
+        ("environments.1.ratio", "0.50\n"),
+        ("environments.1.owner", "null\n"),
+    ];
+
+    for (path, expected) in cases.iter() {
+        assert_eq!(get_value(path, GetOutput::Scalar).unwrap(), *expected);
+    }
+}
+
+#[test]
+fn test_get_collection() {
+    let yaml = "\
+url: \"https://prod.example.com\"
+hosts:
+  - a
+  - b
+replicas: 2
+";
+    let json = "\
+{
+  \"url\": \"https://prod.example.com\",
+  \"hosts\": [
+    \"a\",
+    \"b\"
+  ],
+  \"replicas\": 2
+}
+";
+
+    assert_eq!(
+        get_value("environments.prod", GetOutput::Yaml).unwrap(),
+        yaml
+    );
+    assert_eq!(
+        get_value("environments.prod", GetOutput::Json).unwrap(),
+        json
+    );
+    assert_eq!(
+        get_value("environments.prod.url", GetOutput::Json).unwrap(),
+        "\"https://prod.example.com\"\n",
+    );
+}
+
+#[test]
+fn test_get_errors() {
+    let not_found = get_value("environments.staging.url", GetOutput::Scalar).unwrap_err();
+    let index = get_value("environments.prod.hosts.2", GetOutput::Scalar).unwrap_err();
+    let scalar = get_value("defaults.url.host", GetOutput::Scalar).unwrap_err();
+    let collection = get_value("", GetOutput::Scalar).unwrap_err();
+
+    assert_eq!(not_found.to_string(), "`environments.staging` not found");
+    assert!(not_found.is_missing());
+    assert_eq!(index.to_string(), "`environments.prod.hosts.2` not found");
+    assert!(index.is_missing());
+    assert_eq!(
+        scalar.to_string(),
+        "`defaults.url` is not a mapping or sequence",
+    );
+    assert!(scalar.is_missing());
+    assert_eq!(
+        collection.to_string(),
+        "`.` is not a scalar; use `--output yaml` or `--output json`",
+    );
+    assert!(!collection.is_missing());
+}
+
+#[test]
+fn test_get_quoted_keys() {
+    let raw = "\
+.template:
+  image: alpine
+example.com:
+  <<: {host: example}
+  port: 80
+";
+    let docs = load_merged(raw).unwrap();
+    let cases = [
+        ("'.template'.image", "alpine\n"),
+        ("\\.template.image", "alpine\n"),
+        ("'example.com'.port", "80\n"),
+        ("example\\.com.host", "example\n"),
+    ];
+
+    for (path, expected) in cases.iter() {
+        let value = get(&docs[0], path).unwrap();
+        assert_eq!(
+            format_value(value, path, GetOutput::Scalar).unwrap(),
+            *expected,
+        );
+    }
+
+    let not_found = get(&docs[0], "example\\.com.user").unwrap_err();
+    assert_eq!(not_found.to_string(), "`'example.com'.user` not found");
+    assert!(not_found.is_missing());
+
+    let invalid = get(&docs[0], "'example.com.port").unwrap_err();
+    assert_eq!(
+        invalid.to_string(),
+        "unterminated quote in path `'example.com.port`",
+    );
+    assert!(!invalid.is_missing());
+}
+
+#[test]
+fn test_path_parse() {
+    let cases: &[(&str, &[&str])] = &[
+        ("", &[]),
+        (".", &[]),
+        ("a.b.0", &["a", "b", "0"]),
+        (".a..b.", &["a", "b"]),
+        ("'.a'.b", &[".a", "b"]),
+        ("a\\.b.c", &["a.b", "c"]),
+        ("'it''s'.a\\\\b", &["it's", "a\\b"]),
+        ("a.''", &["a", ""]),
+        ("a'.b'c", &["a.bc"]),
+    ];
+
+    for (raw, expected) in cases.iter() {
+        let segments = path::parse(raw).unwrap();
+        assert_eq!(segments, *expected);
+        assert_eq!(path::parse(&path::format(&segments)).unwrap(), segments);
+    }
+
+    assert_eq!(
+        path::format(&["a.b", "it's", "", "c"]),
+        "'a.b'.'it''s'.''.c",
+    );
+    assert_eq!(path::format::<&str>(&[]), ".");
+    assert!(matches!(
+        path::parse("a.'b"),
+        Err(PathError::UnterminatedQuote(_)),
+    ));
+    assert!(matches!(
+        path::parse("a\\"),
+        Err(PathError::TrailingEscape(_)),
+    ));
+}
+
+#[test]
+fn test_changed_paths() {
+    let event = |kind, path: &str| Event::new(kind).add_path(path.into());
+    let events = vec![
+        event(
+            EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+            "/ci/b.yaml",
+        ),
+        event(EventKind::Create(CreateKind::File), "/ci/a.yml"),
+        event(EventKind::Modify(ModifyKind::Any), "/ci/b.yaml"),
+        event(EventKind::Create(CreateKind::File), "/ci/notes.txt"),
+        event(EventKind::Remove(RemoveKind::File), "/ci/c.yaml"),
+        event(EventKind::Access(AccessKind::Any), "/ci/d.yaml"),
+        event(EventKind::Create(CreateKind::File), "/other/e.yaml"),
+    ];
+    let watched = Watched::new(vec!["/ci".into()], BTreeSet::new());
+
+    assert_eq!(
+        changed_paths(events, &watched)
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [Path::new("/ci/a.yml"), Path::new("/ci/b.yaml")],
+    );
+}
+
+#[test]
+fn test_changed_paths_explicit() {
+    let event = |path: &str| Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.into());
+    let events = vec![
+        event("/ci/ci.conf"),
+        event("/ci/other.conf"),
+        event("/ci/other.yaml"),
+    ];
+    let files = vec![PathBuf::from("/ci/ci.conf")].into_iter().collect();
+    let watched = Watched::new(Vec::new(), files);
+
+    // Only the explicit file is reported even though its directory is watched.
+    assert_eq!(
+        changed_paths(events, &watched)
+            .into_iter()
+            .collect::<Vec<_>>(),
+        [Path::new("/ci/ci.conf")],
+    );
+}
+
+#[test]
+fn test_watch_replaced_file() {
+    let dir = TestDir::new("watch-replace");
+    let file = dir.write("w.yml", "a: 1\n");
+    dir.write("other.yml", "a: 1\n");
+    let notifications = Notifications::new(slice::from_ref(&file)).unwrap();
+    let file = fs::canonicalize(file).unwrap();
+    let timeout = Some(Duration::from_secs(5));
+    // Events for other files may arrive separately.
+    let next = || {
+        loop {
+            let changed = notifications
+                .wait(timeout)
+                .expect("no changes were noticed");
+            if !changed.is_empty() {
+                return changed.into_iter().collect::<Vec<_>>();
+            }
+        }
+    };
+
+    // Editors often write a new file and rename it over the original.
+    let tmp = dir.write("w.tmp", "a: {<<: 1}\n");
+    fs::rename(tmp, &file).unwrap();
+    assert_eq!(next(), [file.as_path()]);
+
+    // The replaced file is still watched.
+    fs::write(&file, "a: 2\n").unwrap();
+    dir.write("other.yml", "a: 2\n");
+    assert_eq!(next(), [file.as_path()]);
+}
+
+#[test]
+fn test_watch_report() {
+    let raw = "a: {<<: {b: 1}}\n";
+
+    assert_eq!(report("ci.yml", raw, true), "ci.yml: ok\n");
+    assert_eq!(
+        report("ci.yml", raw, false),
+        "==> ci.yml <==\n---\na:\n  b: 1\n",
+    );
+    assert_eq!(
+        report("ci.yml", "a:\n  <<: 1\n", false),
+        "ci.yml:2:3: only mappings and arrays of mappings may be merged\n",
+    );
+}
@@ -0,0 +1,212 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Re-validate files as they change.
+//!
+//! Changes are noticed using the platform's filesystem notifications (e.g., inotify). Editors
+//! tend to write files in several steps, so events are gathered until things settle down before
+//! files are handled.
+
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::{check, flatten, in_place};
+
+/// How long to wait for more events after a change.
+const SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// The files and directories being watched.
+#[derive(Debug)]
+pub struct Watched {
+    /// Directories whose YAML files are watched.
+    dirs: Vec<PathBuf>,
+    /// Files given explicitly, which are watched whatever their extension.
+    files: BTreeSet<PathBuf>,
+}
+
+impl Watched {
+    /// Watch YAML files within directories and explicitly given files.
+    pub fn new(dirs: Vec<PathBuf>, files: BTreeSet<PathBuf>) -> Self {
+        Self {
+            dirs,
+            files,
+        }
+    }
+
+    /// Watch the given files and directories.
+    ///
+    /// Paths are canonicalized since events for them use absolute paths.
+    pub fn from_paths(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut dirs = Vec::new();
+        let mut files = BTreeSet::new();
+        for path in paths {
+            let path = fs::canonicalize(path)?;
+            if path.is_dir() {
+                dirs.push(path);
+            } else {
+                files.insert(path);
+            }
+        }
+        Ok(Self::new(dirs, files))
+    }
+
+    /// Whether a path is watched.
+    fn contains(&self, path: &Path) -> bool {
+        self.files.contains(path)
+            || (in_place::is_yaml(path) && self.dirs.iter().any(|dir| path.starts_with(dir)))
+    }
+
+    /// The paths to give to the watcher.
+    ///
+    /// Editors often save files by renaming a new file over them, so files are watched through
+    /// their parent directory rather than by their own inode.
+    fn watches(&self) -> Vec<(&Path, RecursiveMode)> {
+        let parents = self
+            .files
+            .iter()
+            .filter_map(|file| file.parent())
+            .filter(|parent| !self.dirs.iter().any(|dir| parent.starts_with(dir)))
+            .collect::<BTreeSet<_>>();
+        self.dirs
+            .iter()
+            .map(|dir| (dir.as_path(), RecursiveMode::Recursive))
+            .chain(
+                parents
+                    .into_iter()
+                    .map(|parent| (parent, RecursiveMode::NonRecursive)),
+            )
+            .collect()
+    }
+}
+
+/// The watched files changed by filesystem events.
+pub fn changed_paths<I>(events: I, watched: &Watched) -> BTreeSet<PathBuf>
+where
+    I: IntoIterator<Item = Event>,
+{
+    events
+        .into_iter()
+        .filter(|event| {
+            matches!(
+                event.kind,
+                EventKind::Create(_)
+                    | EventKind::Modify(ModifyKind::Any)
+                    | EventKind::Modify(ModifyKind::Data(_))
+                    | EventKind::Modify(ModifyKind::Name(_)),
+            )
+        })
+        .flat_map(|event| event.paths)
+        .filter(|path| watched.contains(path))
+        .collect()
+}
+
+/// Filesystem notifications for watched files.
+pub struct Notifications {
+    // The watcher stops when dropped.
+    _watcher: RecommendedWatcher,
+    rx: Receiver<notify::Result<Event>>,
+    watched: Watched,
+}
+
+impl Notifications {
+    /// Start watching files and directories.
+    pub fn new(paths: &[PathBuf]) -> Result<Self, String> {
+        let watched = Watched::from_paths(paths).map_err(|err| err.to_string())?;
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx).map_err(|err| err.to_string())?;
+        for (path, mode) in watched.watches() {
+            watcher
+                .watch(path, mode)
+                .map_err(|err| format!("{}: {}", path.display(), err))?;
+        }
+
+        Ok(Self {
+            _watcher: watcher,
+            rx,
+            watched,
+        })
+    }
+
+    /// Wait for the next changes to watched files.
+    ///
+    /// Returns `None` when notifications stop (or, with a timeout, if nothing happens in time).
+    /// Errors from the notification backend are reported and otherwise ignored.
+    pub fn wait(&self, timeout: Option<Duration>) -> Option<BTreeSet<PathBuf>> {
+        let event = match timeout {
+            Some(timeout) => self.rx.recv_timeout(timeout).ok()?,
+            None => self.rx.recv().ok()?,
+        };
+        let mut events = vec![event];
+        while let Ok(event) = self.rx.recv_timeout(SETTLE_TIME) {
+            events.push(event);
+        }
+
+        let events = events.into_iter().filter_map(|event| {
+            event
+                .map_err(|err| eprintln!("yaml-merge-keys: {}", err))
+                .ok()
+        });
+        Some(changed_paths(events, &self.watched))
+    }
+}
+
+/// Describe the result of handling a file's merge keys.
+///
+/// Errors are reported as diagnostics. Otherwise, the flattened documents are included unless
+/// only checking.
+pub fn report(name: &str, source: &str, check_only: bool) -> String {
+    match yaml_merge_keys::load_merged(source) {
+        Ok(docs) => {
+            if check_only {
+                format!("{}: ok\n", name)
+            } else {
+                let out = flatten::emit(&docs).unwrap_or_else(|err| format!("{:?}\n", err));
+                format!("==> {} <==\n{}", name, out)
+            }
+        },
+        Err(err) => format!("{}\n", check::diagnostic(name, &err)),
+    }
+}
+
+fn handle(path: &Path, check_only: bool) {
+    // Events use absolute paths; show them relative to the working directory where possible.
+    let cwd = env::current_dir().and_then(fs::canonicalize).ok();
+    let relative = cwd.as_ref().and_then(|cwd| path.strip_prefix(cwd).ok());
+    let name = relative.unwrap_or(path).display().to_string();
+    // Files may be removed (or replaced) again before they are handled.
+    if let Ok(source) = fs::read_to_string(path) {
+        print!("{}", report(&name, &source, check_only));
+    }
+}
+
+/// Watch files and directories, reporting on YAML files whenever they change.
+///
+/// Each file is checked once at the start. Files given explicitly are reported on whatever their
+/// extension. Errors from the notification backend are reported and watching continues.
+pub fn watch(paths: &[PathBuf], check_only: bool) -> Result<(), String> {
+    let notifications = Notifications::new(paths)?;
+
+    let files = in_place::expand_paths(paths.to_vec()).map_err(|err| err.to_string())?;
+    for file in &files {
+        handle(file, true);
+    }
+
+    while let Some(changed) = notifications.wait(None) {
+        for path in changed {
+            handle(&path, check_only);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,118 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::slice;
+
+use yaml_rust::scanner::Marker;
+use yaml_rust::yaml::Hash;
+use yaml_rust::Yaml;
+
+use crate::anchors::AnchorTable;
+use crate::loader::{load_with_merge_key_markers, MarkedDocuments};
+use crate::node::MERGE_KEY;
+use crate::{merge_keys_with_anchors, Error, MergeKeyError};
+
+/// Find the first merge key in document order which fails to merge.
+///
+/// Merge keys within a merge value are checked before the merge key itself so that the innermost
+/// failing merge key is found.
+fn find_merge_error(
+    node: &Yaml,
+    markers: &mut slice::Iter<Marker>,
+    anchors: &AnchorTable,
+) -> Option<(Option<Marker>, MergeKeyError)> {
+    match node {
+        Yaml::Hash(hash) => {
+            hash.iter().find_map(|(key, value)| {
+                if matches!(key, Yaml::String(s) if s == MERGE_KEY) {
+                    let marker = markers.next().copied();
+                    find_merge_error(value, markers, anchors).or_else(|| {
+                        let mut merge = Hash::new();
+                        merge.insert(key.clone(), value.clone());
+                        merge_keys_with_anchors(Yaml::Hash(merge), anchors)
+                            .err()
+                            .map(|err| (marker, err))
+                    })
+                } else {
+                    find_merge_error(key, markers, anchors)
+                        .or_else(|| find_merge_error(value, markers, anchors))
+                }
+            })
+        },
+        Yaml::Array(arr) => {
+            arr.iter()
+                .find_map(|item| find_merge_error(item, markers, anchors))
+        },
+        _ => None,
+    }
+}
+
+/// Attach the position of the failing merge key to an error.
+fn locate_error(
+    error: MergeKeyError,
+    doc: &Yaml,
+    markers: &[Marker],
+    anchors: &AnchorTable,
+) -> Error {
+    if error.location().is_some() {
+        return error.into();
+    }
+
+    match find_merge_error(doc, &mut markers.iter(), anchors) {
+        Some((_, error)) if error.location().is_some() => error.into(),
+        Some((Some(marker), error)) => {
+            Error::MergeKeyAt {
+                error,
+                location: (&marker).into(),
+            }
+        },
+        _ => error.into(),
+    }
+}
+
+/// Handle the merge keys of loaded documents.
+///
+/// Errors are located using the documents from `reload`, which loads the source again.
+pub(crate) fn merge_located<F>(
+    docs: MarkedDocuments,
+    anchors: &AnchorTable,
+    mut reload: F,
+) -> Result<Vec<Yaml>, Error>
+where
+    F: FnMut() -> (MarkedDocuments, AnchorTable),
+{
+    docs.into_iter()
+        .enumerate()
+        .map(|(idx, (doc, _))| {
+            merge_keys_with_anchors(doc, anchors).map_err(|error| {
+                // The document has been consumed; load it again to find the failing merge key.
+                // This only happens when there is an error, so the common path stays cheap.
+                let (mut docs, anchors) = reload();
+                let (doc, markers) = docs.swap_remove(idx);
+                locate_error(error, &doc, &markers, &anchors)
+            })
+        })
+        .collect()
+}
+
+/// Load YAML documents from a string and handle their merge keys.
+///
+/// Errors from handling merge keys are reported with the position of the merge key which caused
+/// them (or the position of the alias for undefined aliases).
+pub fn load_merged(source: &str) -> Result<Vec<Yaml>, Error> {
+    let (docs, anchors) = load_with_merge_key_markers(source)?;
+    merge_located(docs, &anchors, || {
+        load_with_merge_key_markers(source)
+            .expect("the source has already been parsed successfully")
+    })
+}
+
+/// Check that the merge keys in YAML documents may be handled.
+///
+/// This is [`load_merged`] without keeping the documents.
+pub fn check(source: &str) -> Result<(), Error> {
+    load_merged(source).map(|_| ())
+}
@@ -0,0 +1,97 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::io;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, DeserializeOwned};
+use serde_yaml::{Deserializer, Value};
+
+use crate::{merge_keys_serde, Error};
+
+/// Merge keys in a parsed document and deserialize it.
+fn from_value<T>(doc: Value) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    let merged = merge_keys_serde(doc)?;
+    Ok(serde_yaml::from_value(merged)?)
+}
+
+/// Deserialize a YAML document from a string after handling its merge keys.
+pub fn from_str<T>(source: &str) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    from_value(serde_yaml::from_str(source)?)
+}
+
+/// Deserialize a YAML document from bytes after handling its merge keys.
+pub fn from_slice<T>(source: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned,
+{
+    from_value(serde_yaml::from_slice(source)?)
+}
+
+/// Deserialize a YAML document from a reader after handling its merge keys.
+pub fn from_reader<R, T>(reader: R) -> Result<T, Error>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    from_value(serde_yaml::from_reader(reader)?)
+}
+
+/// An iterator over the documents of a multi-document YAML stream.
+///
+/// Merge keys are handled in each document before it is deserialized. Anchors are not shared
+/// between documents.
+pub struct Documents<'de, T> {
+    documents: Deserializer<'de>,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<'de, T> Documents<'de, T> {
+    fn new(documents: Deserializer<'de>) -> Self {
+        Self {
+            documents,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Iterator for Documents<'_, T>
+where
+    T: DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.documents.next().map(|document| {
+            let doc = Value::deserialize(document)?;
+            from_value(doc)
+        })
+    }
+}
+
+/// Deserialize each YAML document in a string after handling its merge keys.
+pub fn from_str_documents<T>(source: &str) -> Documents<'_, T> {
+    Documents::new(Deserializer::from_str(source))
+}
+
+/// Deserialize each YAML document in bytes after handling its merge keys.
+pub fn from_slice_documents<T>(source: &[u8]) -> Documents<'_, T> {
+    Documents::new(Deserializer::from_slice(source))
+}
+
+/// Deserialize each YAML document from a reader after handling its merge keys.
+pub fn from_reader_documents<'de, R, T>(reader: R) -> Documents<'de, T>
+where
+    R: io::Read + 'de,
+{
+    Documents::new(Deserializer::from_reader(reader))
+}
@@ -0,0 +1,417 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde_yaml::{Mapping, Value};
+
+use crate::merge_keys::merge_into_mapping;
+use crate::node::MERGE_KEY;
+
+/// A `Deserializer` which handles merge keys while deserializing.
+///
+/// Values are passed through from the wrapped deserializer as they are read. For each mapping,
+/// only its keys and its merge value are buffered; entries from the merge value which are not
+/// overridden are presented to the visitor after the mapping's own entries.
+///
+/// ```rust
+/// # extern crate serde;
+/// # extern crate serde_yaml;
+/// # extern crate yaml_merge_keys;
+/// use serde::Deserialize;
+/// use yaml_merge_keys::MergeKeysDeserializer;
+///
+/// let raw = "\
+/// base: &base
+///     a: 1
+/// dict:
+///     <<: *base
+///     b: 2
+/// ";
+/// let de = serde_yaml::Deserializer::from_str(raw);
+/// let doc = serde_yaml::Value::deserialize(MergeKeysDeserializer::new(de)).unwrap();
+///
+/// assert_eq!(doc["dict"]["a"], 1);
+/// ```
+pub struct MergeKeysDeserializer<'de, D> {
+    de: D,
+    _phantom: PhantomData<&'de ()>,
+}
+
+impl<'de, D> MergeKeysDeserializer<'de, D>
+where
+    D: Deserializer<'de>,
+{
+    /// Wrap a deserializer.
+    pub fn new(de: D) -> Self {
+        Self {
+            de,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ( $( $method:ident ( $( $arg:ident: $ty:ty ),* ), )* ) => {
+        $(
+            fn $method<V>(self, $( $arg: $ty, )* visitor: V) -> Result<V::Value, Self::Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.de.$method($( $arg, )* MergeKeysVisitor(visitor))
+            }
+        )*
+    };
+}
+
+impl<'de, D> Deserializer<'de> for MergeKeysDeserializer<'de, D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    forward_deserialize! {
+        deserialize_any(),
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_i128(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_u128(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_option(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+        deserialize_identifier(),
+        deserialize_ignored_any(),
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.de.is_human_readable()
+    }
+}
+
+/// A seed which deserializes through a `MergeKeysDeserializer`.
+struct MergeKeysSeed<S>(S);
+
+impl<'de, S> DeserializeSeed<'de> for MergeKeysSeed<S>
+where
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.deserialize(MergeKeysDeserializer::new(deserializer))
+    }
+}
+
+/// Deserialize a value into a buffer with merge keys handled.
+fn buffered() -> MergeKeysSeed<PhantomData<Value>> {
+    MergeKeysSeed(PhantomData)
+}
+
+macro_rules! forward_visit {
+    ( $( $method:ident ( $ty:ty ), )* ) => {
+        $(
+            fn $method<E>(self, v: $ty) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                self.0.$method(v)
+            }
+        )*
+    };
+}
+
+/// A visitor which handles merge keys in the data given to the wrapped visitor.
+struct MergeKeysVisitor<V>(V);
+
+impl<'de, V> Visitor<'de> for MergeKeysVisitor<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    forward_visit! {
+        visit_bool(bool),
+        visit_i8(i8),
+        visit_i16(i16),
+        visit_i32(i32),
+        visit_i64(i64),
+        visit_i128(i128),
+        visit_u8(u8),
+        visit_u16(u16),
+        visit_u32(u32),
+        visit_u64(u64),
+        visit_u128(u128),
+        visit_f32(f32),
+        visit_f64(f64),
+        visit_char(char),
+        visit_str(&str),
+        visit_borrowed_str(&'de str),
+        visit_string(String),
+        visit_bytes(&[u8]),
+        visit_borrowed_bytes(&'de [u8]),
+        visit_byte_buf(Vec<u8>),
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_none()
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.0.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0.visit_some(MergeKeysDeserializer::new(deserializer))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        self.0
+            .visit_newtype_struct(MergeKeysDeserializer::new(deserializer))
+    }
+
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.0.visit_seq(MergeKeysSeqAccess(seq))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        self.0.visit_map(MergeKeysMapAccess {
+            map,
+            seen: HashSet::new(),
+            merge_value: None,
+            merged: None,
+            value: None,
+        })
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        self.0.visit_enum(MergeKeysEnumAccess(data))
+    }
+}
+
+/// Sequence access which handles merge keys in its elements.
+struct MergeKeysSeqAccess<A>(A);
+
+impl<'de, A> SeqAccess<'de> for MergeKeysSeqAccess<A>
+where
+    A: SeqAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.0.next_element_seed(MergeKeysSeed(seed))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+/// Map access which applies the merge key of the mapping.
+///
+/// Entries of the mapping are passed through as they are read. Keys are kept to know which merged
+/// entries are overridden and the merge value is set aside. Once the wrapped mapping is
+/// exhausted, the merged entries which were not overridden are presented.
+struct MergeKeysMapAccess<A> {
+    map: A,
+    seen: HashSet<Value>,
+    merge_value: Option<Value>,
+    merged: Option<<Mapping as IntoIterator>::IntoIter>,
+    value: Option<Value>,
+}
+
+impl<'de, A> MergeKeysMapAccess<A>
+where
+    A: MapAccess<'de>,
+{
+    /// Get the next key from the wrapped mapping, setting aside any merge value.
+    fn next_local_key(&mut self) -> Result<Option<Value>, A::Error> {
+        while let Some(key) = self.map.next_key_seed(buffered())? {
+            if matches!(&key, Value::String(s) if s == MERGE_KEY) {
+                self.merge_value = Some(self.map.next_value_seed(buffered())?);
+            } else {
+                self.seen.insert(key.clone());
+                return Ok(Some(key));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Get the next merged entry which has not been overridden.
+    fn next_merged_entry(&mut self) -> Result<Option<(Value, Value)>, A::Error> {
+        let merged = match &mut self.merged {
+            Some(merged) => merged,
+            None => {
+                let mapping = if let Some(merge_value) = self.merge_value.take() {
+                    merge_into_mapping(Mapping::new(), merge_value).map_err(de::Error::custom)?
+                } else {
+                    Mapping::new()
+                };
+                self.merged.get_or_insert(mapping.into_iter())
+            },
+        };
+        let seen = &self.seen;
+        Ok(merged.find(|(key, _)| !seen.contains(key)))
+    }
+}
+
+impl<'de, A> MapAccess<'de> for MergeKeysMapAccess<A>
+where
+    A: MapAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        let key = if self.merged.is_none() {
+            self.next_local_key()?
+        } else {
+            None
+        };
+        let key = match key {
+            Some(key) => key,
+            None => {
+                match self.next_merged_entry()? {
+                    Some((key, value)) => {
+                        self.value = Some(value);
+                        key
+                    },
+                    None => return Ok(None),
+                }
+            },
+        };
+
+        seed.deserialize(key).map(Some).map_err(de::Error::custom)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        if let Some(value) = self.value.take() {
+            seed.deserialize(value).map_err(de::Error::custom)
+        } else {
+            self.map.next_value_seed(MergeKeysSeed(seed))
+        }
+    }
+}
+
+/// Enum access which handles merge keys in its variant data.
+struct MergeKeysEnumAccess<A>(A);
+
+impl<'de, A> EnumAccess<'de> for MergeKeysEnumAccess<A>
+where
+    A: EnumAccess<'de>,
+{
+    type Error = A::Error;
+    type Variant = MergeKeysVariantAccess<A::Variant>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.0
+            .variant_seed(seed)
+            .map(|(value, variant)| (value, MergeKeysVariantAccess(variant)))
+    }
+}
+
+/// Variant access which handles merge keys in its data.
+struct MergeKeysVariantAccess<A>(A);
+
+impl<'de, A> VariantAccess<'de> for MergeKeysVariantAccess<A>
+where
+    A: VariantAccess<'de>,
+{
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        self.0.unit_variant()
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.0.newtype_variant_seed(MergeKeysSeed(seed))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.tuple_variant(len, MergeKeysVisitor(visitor))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.0.struct_variant(fields, MergeKeysVisitor(visitor))
+    }
+}
@@ -0,0 +1,220 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use thiserror::Error;
+use yaml_rust::scanner::{Marker, ScanError};
+
+use crate::include::{IncludeError, IncludeSite};
+use crate::MergeKeyError;
+
+/// Errors which may occur when loading a document with merge keys.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The document could not be parsed by `yaml-rust`.
+    #[error(transparent)]
+    Scan(#[from] ScanError),
+    /// The document could not be parsed or deserialized by `serde_yaml`.
+    #[cfg(feature = "serde_yaml")]
+    #[error(transparent)]
+    Serde(#[from] serde_yaml::Error),
+    /// Merge keys in the document could not be handled.
+    #[error(transparent)]
+    MergeKey(#[from] MergeKeyError),
+    /// A merge key in the document could not be handled.
+    #[error("{}", error)]
+    MergeKeyAt {
+        /// The error.
+        error: MergeKeyError,
+        /// The position of the merge key.
+        location: Location,
+    },
+    /// An `!include` directive could not be handled.
+    #[error("{}{}", error, describe_chain(chain))]
+    Include {
+        /// The error.
+        error: IncludeError,
+        /// The directives leading to the error, starting from the loaded file.
+        chain: Vec<IncludeSite>,
+    },
+}
+
+fn describe_chain(chain: &[IncludeSite]) -> String {
+    if chain.is_empty() {
+        String::new()
+    } else {
+        let sites = chain.iter().map(ToString::to_string).collect::<Vec<_>>();
+        format!(" (included from {})", sites.join(" -> "))
+    }
+}
+
+/// The kind of an error.
+///
+/// Intended for matching on errors without depending on the underlying error types.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The document is not valid YAML.
+    Syntax,
+    /// The document could not be deserialized into the requested type.
+    Deserialize,
+    /// A non-mapping value was used as a merge value.
+    InvalidMergeValue,
+    /// An alias refers to an anchor which is not available.
+    UnresolvedAlias,
+    /// An alias refers to an anchor which is not defined at that point in the document.
+    UndefinedAlias,
+    /// An anchored node contains an alias to itself.
+    RecursiveAlias,
+    /// A tagged value was used as a merge value.
+    TaggedMergeValue,
+    /// A merge source has a different tag than the mapping it is merged into.
+    TagMismatch,
+    /// An `!include` directive could not be handled.
+    Include,
+}
+
+/// The offset of a position from the start of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "serde_yaml"), allow(dead_code))]
+enum Offset {
+    /// An offset in bytes.
+    Bytes(usize),
+    /// An offset in characters.
+    Chars(usize),
+}
+
+/// A position within a document.
+///
+/// Lines and columns are the same for every backend. The offset of the position is counted in
+/// characters by `yaml-rust` and in bytes by `serde_yaml`, so only one of [`byte_index`] and
+/// [`char_index`] is available for a given location.
+///
+/// [`byte_index`]: Location::byte_index
+/// [`char_index`]: Location::char_index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Location {
+    offset: Offset,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    /// The byte offset of the position, for positions reported by `serde_yaml`.
+    pub fn byte_index(&self) -> Option<usize> {
+        match self.offset {
+            Offset::Bytes(index) => Some(index),
+            Offset::Chars(_) => None,
+        }
+    }
+
+    /// The character offset of the position, for positions reported by `yaml-rust`.
+    pub fn char_index(&self) -> Option<usize> {
+        match self.offset {
+            Offset::Chars(index) => Some(index),
+            Offset::Bytes(_) => None,
+        }
+    }
+
+    /// The line of the position (1-based).
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column of the position (1-based).
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl From<&Marker> for Location {
+    fn from(marker: &Marker) -> Self {
+        Location {
+            offset: Offset::Chars(marker.index()),
+            line: marker.line(),
+            // `yaml-rust` columns are 0-based.
+            column: marker.col() + 1,
+        }
+    }
+}
+
+#[cfg(feature = "serde_yaml")]
+impl From<serde_yaml::Location> for Location {
+    fn from(location: serde_yaml::Location) -> Self {
+        Location {
+            offset: Offset::Bytes(location.index()),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+impl MergeKeyError {
+    /// The kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            MergeKeyError::InvalidMergeValue => ErrorKind::InvalidMergeValue,
+            MergeKeyError::UnresolvedAlias(_) => ErrorKind::UnresolvedAlias,
+            MergeKeyError::UndefinedAlias {
+                ..
+            } => ErrorKind::UndefinedAlias,
+            MergeKeyError::RecursiveAlias(_) => ErrorKind::RecursiveAlias,
+            MergeKeyError::TaggedMergeValue(_) => ErrorKind::TaggedMergeValue,
+            MergeKeyError::TagMismatch {
+                ..
+            } => ErrorKind::TagMismatch,
+        }
+    }
+
+    /// The location of the error in the document, if known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            MergeKeyError::UndefinedAlias {
+                marker, ..
+            } => marker.as_ref().map(Into::into),
+            _ => None,
+        }
+    }
+}
+
+impl Error {
+    /// The kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Scan(_) => ErrorKind::Syntax,
+            // Documents are read into a `Value` before being deserialized by the typed helpers, so
+            // only errors found while reading the document have locations.
+            #[cfg(feature = "serde_yaml")]
+            Error::Serde(err) if err.location().is_some() => ErrorKind::Syntax,
+            #[cfg(feature = "serde_yaml")]
+            Error::Serde(_) => ErrorKind::Deserialize,
+            Error::MergeKey(err) => err.kind(),
+            Error::MergeKeyAt {
+                error, ..
+            } => error.kind(),
+            Error::Include {
+                ..
+            } => ErrorKind::Include,
+        }
+    }
+
+    /// The location of the error in the document, if known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            Error::Scan(err) => Some(err.marker().into()),
+            #[cfg(feature = "serde_yaml")]
+            Error::Serde(err) => err.location().map(Into::into),
+            Error::MergeKey(err) => err.location(),
+            Error::MergeKeyAt {
+                location, ..
+            } => Some(*location),
+            // The position of the directive in the loaded file.
+            Error::Include {
+                chain, ..
+            } => chain.first().map(IncludeSite::location),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use thiserror::Error;
+use yaml_rust::scanner::Marker;
+use yaml_rust::Yaml;
+
+use crate::check::merge_located;
+use crate::error::{Error, Location};
+use crate::loader::load_with_includes;
+
+/// The suffix of the `!include` tag.
+pub(crate) const INCLUDE_TAG: &str = "include";
+
+/// Errors which may occur when handling `!include` directives.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum IncludeError {
+    /// An `!include` directive was used other than as a merge source.
+    #[error("`!include` may only be used as a merge source")]
+    NotMergeSource,
+    /// An included file could not be read.
+    #[error("failed to read `{}`: {}", path.display(), source)]
+    Read {
+        /// The path of the file.
+        path: PathBuf,
+        /// The error.
+        source: io::Error,
+    },
+    /// An included file is outside of the include root.
+    #[error("`{}` is outside of the include root `{}`", path.display(), root.display())]
+    OutsideRoot {
+        /// The path of the file.
+        path: PathBuf,
+        /// The include root.
+        root: PathBuf,
+    },
+    /// A file includes itself (possibly through other files).
+    #[error("`{}` includes itself", path.display())]
+    Cycle {
+        /// The path of the file.
+        path: PathBuf,
+    },
+    /// An included file does not contain exactly one document.
+    #[error("`{}` must contain exactly one document (found {})", path.display(), count)]
+    DocumentCount {
+        /// The path of the file.
+        path: PathBuf,
+        /// The number of documents in the file.
+        count: usize,
+    },
+    /// An included file could not be loaded.
+    #[error("`{}`: {}", path.display(), error)]
+    Invalid {
+        /// The path of the file.
+        path: PathBuf,
+        /// The error.
+        error: Box<Error>,
+    },
+}
+
+/// Resolve the `.` and `..` components of a path without accessing the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// The position of an `!include` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncludeSite {
+    file: PathBuf,
+    location: Location,
+}
+
+impl IncludeSite {
+    /// The file containing the directive.
+    ///
+    /// Files within the include root are relative to it.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// The position of the directive.
+    pub fn location(&self) -> Location {
+        self.location
+    }
+}
+
+impl fmt::Display for IncludeSite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.file.display(),
+            self.location.line(),
+            self.location.column(),
+        )
+    }
+}
+
+/// Loads documents which use `!include` directives for merge sources from other files.
+///
+/// Since anchors may not be shared between files, merge sources may instead be loaded from
+/// another file with `<<: !include path/to/base.yml` (or as an item of a merge sequence such as
+/// `<<: [*local, !include base.yml]`). The included file must contain a single document which
+/// is merged in after its own merge keys (and includes) are handled.
+///
+/// Paths are relative to the including file and must be within the include root. Each file is
+/// loaded once and reused for later includes. Errors from `!include` directives are reported
+/// with the chain of includes which led to them.
+#[derive(Debug)]
+pub struct Includes {
+    root: PathBuf,
+    cache: HashMap<PathBuf, Yaml>,
+    /// The files currently being loaded.
+    stack: Vec<PathBuf>,
+}
+
+impl Includes {
+    /// Create a loader which may include files within a root directory.
+    pub fn new<P>(root: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(Self {
+            root: fs::canonicalize(root)?,
+            cache: HashMap::new(),
+            stack: Vec::new(),
+        })
+    }
+
+    /// The directory included files must be within.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Load YAML documents from a file and handle their merge keys and includes.
+    ///
+    /// The file itself does not need to be within the include root.
+    pub fn load_merged<P>(&mut self, path: P) -> Result<Vec<Yaml>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let read_error = |source| {
+            Error::Include {
+                error: IncludeError::Read {
+                    path: path.into(),
+                    source,
+                },
+                chain: Vec::new(),
+            }
+        };
+        let path = fs::canonicalize(path).map_err(read_error)?;
+        let source = fs::read_to_string(&path).map_err(read_error)?;
+
+        self.load_file(&source, &path)
+    }
+
+    fn load_file(&mut self, source: &str, path: &Path) -> Result<Vec<Yaml>, Error> {
+        self.stack.push(path.into());
+        let res = load_with_includes(source, path, self).and_then(|(docs, anchors)| {
+            merge_located(docs, &anchors, || {
+                // Included files are cached, so loading again does not read them again.
+                load_with_includes(source, path, self)
+                    .expect("the source has already been loaded successfully")
+            })
+        });
+        self.stack.pop();
+        res
+    }
+
+    /// The path of a file for use in errors.
+    fn display_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root).unwrap_or(path).into()
+    }
+
+    /// The site of an `!include` directive.
+    pub(crate) fn site(&self, file: &Path, marker: Marker) -> IncludeSite {
+        IncludeSite {
+            file: self.display_path(file),
+            location: (&marker).into(),
+        }
+    }
+
+    /// Load the document for an `!include` directive.
+    pub(crate) fn include(
+        &mut self,
+        target: &str,
+        from: &Path,
+        marker: Marker,
+    ) -> Result<Yaml, Error> {
+        let site = self.site(from, marker);
+        let at_site = |error| {
+            Error::Include {
+                error,
+                chain: vec![site.clone()],
+            }
+        };
+
+        let joined = from.parent().unwrap_or_else(|| Path::new("")).join(target);
+        let outside_root = || {
+            at_site(IncludeError::OutsideRoot {
+                path: joined.clone(),
+                root: self.root.clone(),
+            })
+        };
+        // Check the path before touching the filesystem so that errors do not reveal whether
+        // files outside of the root exist.
+        if !normalize(&joined).starts_with(&self.root) {
+            return Err(outside_root());
+        }
+        let path = fs::canonicalize(&joined).map_err(|source| {
+            at_site(IncludeError::Read {
+                path: self.display_path(&joined),
+                source,
+            })
+        })?;
+        // Symbolic links may still lead outside of the root.
+        if !path.starts_with(&self.root) {
+            return Err(outside_root());
+        }
+        if self.stack.contains(&path) {
+            return Err(at_site(IncludeError::Cycle {
+                path: self.display_path(&path),
+            }));
+        }
+        if let Some(doc) = self.cache.get(&path) {
+            return Ok(doc.clone());
+        }
+
+        let source = fs::read_to_string(&path).map_err(|source| {
+            at_site(IncludeError::Read {
+                path: self.display_path(&path),
+                source,
+            })
+        })?;
+        let mut docs = self.load_file(&source, &path).map_err(|err| {
+            match err {
+                Error::Include {
+                    error,
+                    mut chain,
+                } => {
+                    chain.insert(0, site.clone());
+                    Error::Include {
+                        error,
+                        chain,
+                    }
+                },
+                err => {
+                    at_site(IncludeError::Invalid {
+                        path: self.display_path(&path),
+                        error: Box::new(err),
+                    })
+                },
+            }
+        })?;
+        if docs.len() != 1 {
+            return Err(at_site(IncludeError::DocumentCount {
+                path: self.display_path(&path),
+                count: docs.len(),
+            }));
+        }
+
+        let doc = docs.remove(0);
+        self.cache.insert(path, doc.clone());
+        Ok(doc)
+    }
+}
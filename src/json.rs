@@ -0,0 +1,69 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::convert::Infallible;
+
+use serde_json::{Map, Value};
+
+use crate::node::{MergeableMapping, MergeableNode, NodeKind, MERGE_KEY};
+use crate::MergeKeyError;
+
+impl MergeableMapping for Map<String, Value> {
+    type Key = String;
+    type Value = Value;
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        Map::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) {
+        Map::insert(self, key, value);
+    }
+}
+
+impl MergeableNode for Value {
+    type Key = String;
+    type Mapping = Map<String, Value>;
+    type Tag = Infallible;
+    type Meta = ();
+
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self> {
+        match self {
+            Value::Object(object) => Ok((NodeKind::Mapping(object), ())),
+            Value::Array(arr) => Ok((NodeKind::Sequence(arr), ())),
+            _ => Err(self),
+        }
+    }
+
+    fn from_parts(kind: NodeKind<Self>, _: Self::Meta) -> Self {
+        match kind {
+            NodeKind::Mapping(object) => Value::Object(object),
+            NodeKind::Sequence(arr) => Value::Array(arr),
+            NodeKind::Tagged(tag, _) => match tag {},
+            // JSON has no aliases; these are never produced by `into_parts`.
+            NodeKind::Alias(_) | NodeKind::Undefined => Value::Null,
+        }
+    }
+
+    fn is_merge_key(key: &Self::Key) -> bool {
+        key == MERGE_KEY
+    }
+
+    fn merge_in_key<E, F>(key: Self::Key, _: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>,
+    {
+        // Object keys are strings and cannot contain merge keys.
+        Ok(key)
+    }
+}
+
+/// Handle merge keys in a JSON document.
+///
+/// Objects with a `"<<"` key are handled in the same way as YAML mappings with a merge key.
+pub fn merge_keys_json(doc: Value) -> Result<Value, MergeKeyError> {
+    crate::merge_keys(doc)
+}
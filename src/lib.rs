@@ -12,8 +12,14 @@
 //! # Usage
 //!
 //! This crate provides a function which implements the [YAML Merge Key extension]. Given a YAML
-//! document from `yaml-rust` (or `serde_yaml` with the `serde_yaml` feature), it will return
-//! a YAML document with the merge keys removed and merged into their owning dictionaries.
+//! document from `yaml-rust` (or `yaml-rust2`, `saphyr`, `serde_yaml`, `serde_yml`, or
+//! `serde_norway` with the feature of the same name), it will return a YAML document with the
+//! merge keys removed and merged into their owning dictionaries. JSON documents from `serde_json`
+//! using `"<<"` keys are supported with the `serde_json` feature. Other document types may be
+//! supported by implementing the [`MergeableNode`] trait.
+//!
+//! With the `serde_yaml` feature, the `from_str`, `from_slice`, and `from_reader` functions
+//! deserialize typed values from YAML documents after handling their merge keys.
 //!
 //! ```rust
 //! # extern crate yaml_rust;
@@ -103,19 +109,111 @@
 
 #![deny(missing_docs)]
 
+mod anchors;
+mod check;
+#[cfg(feature = "serde_yaml")]
+mod de;
+#[cfg(feature = "serde_yaml")]
+mod deserializer;
+mod error;
+mod include;
+#[cfg(feature = "serde_json")]
+mod json;
+mod loader;
+mod marked;
 mod merge_keys;
 #[cfg(feature = "serde_yaml")]
+mod merged;
+mod node;
+mod options;
+#[cfg(feature = "saphyr")]
+mod saphyr_yaml;
+#[cfg(any(
+    feature = "serde_yaml",
+    feature = "serde_yml",
+    feature = "serde_norway"
+))]
 mod serde;
+#[cfg(feature = "yaml-rust2")]
+mod yaml2;
 
+pub use anchors::AnchorTable;
+pub use check::{check, load_merged};
+pub use error::{Error, ErrorKind, Location};
+pub use include::{IncludeError, IncludeSite, Includes};
+pub use loader::{load_marked, load_with_anchors};
+pub use marked::{Marked, MarkedNode};
 pub use merge_keys::merge_keys;
+pub use merge_keys::merge_keys_with_anchors;
+pub use merge_keys::merge_keys_with_options;
 pub use merge_keys::MergeKeyError;
+pub use node::{MergeableMapping, MergeableNode, NodeKind, MERGE_KEY};
+pub use options::{MergeOptions, TagPolicy};
+
+#[cfg(feature = "saphyr")]
+pub use saphyr;
+#[cfg(feature = "saphyr")]
+pub use saphyr_yaml::{merge_keys_saphyr, merge_keys_saphyr_marked};
+
+#[cfg(feature = "serde_json")]
+pub use json::merge_keys_json;
+#[cfg(feature = "serde_json")]
+pub use serde_json;
 
 #[cfg(feature = "serde_yaml")]
-pub use serde::merge_keys_serde;
+pub use self::serde::{merge_keys_serde, merge_keys_serde_with_options};
+#[cfg(feature = "serde_yaml")]
+pub use de::{
+    from_reader, from_reader_documents, from_slice, from_slice_documents, from_str,
+    from_str_documents, Documents,
+};
+#[cfg(feature = "serde_yaml")]
+pub use deserializer::MergeKeysDeserializer;
+#[cfg(feature = "serde_yaml")]
+pub use merged::{deserialize_merged, deserialize_merged_with_options, Merged};
 #[cfg(feature = "serde_yaml")]
 pub use serde_yaml;
 
+#[cfg(feature = "serde_yml")]
+pub use self::serde::{merge_keys_serde_yml, merge_keys_serde_yml_with_options};
+#[cfg(feature = "serde_yml")]
+pub use serde_yml;
+
+#[cfg(feature = "serde_norway")]
+pub use self::serde::{merge_keys_serde_norway, merge_keys_serde_norway_with_options};
+#[cfg(feature = "serde_norway")]
+pub use serde_norway;
+
+#[cfg(feature = "yaml-rust2")]
+pub use yaml2::merge_keys_yaml_rust2;
+#[cfg(feature = "yaml-rust2")]
+pub use yaml_rust2;
+
 #[cfg(test)]
 mod test;
+#[cfg(test)]
+mod test_check;
+#[cfg(all(test, feature = "serde_yaml"))]
+mod test_de;
+#[cfg(all(test, feature = "serde_yaml"))]
+mod test_deserializer;
+#[cfg(test)]
+mod test_dir;
+#[cfg(test)]
+mod test_error;
+#[cfg(test)]
+mod test_include;
+#[cfg(all(test, feature = "serde_json"))]
+mod test_json;
+#[cfg(all(test, feature = "serde_yaml"))]
+mod test_merged;
+#[cfg(test)]
+mod test_node;
+#[cfg(all(test, feature = "saphyr"))]
+mod test_saphyr;
 #[cfg(all(test, feature = "serde_yaml"))]
 mod test_serde;
+#[cfg(all(test, any(feature = "serde_yml", feature = "serde_norway")))]
+mod test_serde_forks;
+#[cfg(all(test, feature = "yaml-rust2"))]
+mod test_yaml2;
@@ -0,0 +1,470 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeSet;
+use std::mem;
+use std::path::Path;
+
+use yaml_rust::parser::{MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, ScanError, TScalarStyle, TokenType};
+use yaml_rust::yaml::Hash;
+use yaml_rust::{Event, Yaml};
+
+use crate::anchors::AnchorTable;
+use crate::error::Error;
+use crate::include::{IncludeError, Includes, INCLUDE_TAG};
+use crate::marked::{Marked, MarkedNode};
+use crate::node::MERGE_KEY;
+
+/// A node type which documents may be loaded into.
+trait LoadedNode: Clone {
+    /// A scalar (or a document loaded for an `!include` directive).
+    fn value(value: Yaml, marker: Marker) -> Self;
+    /// An empty sequence.
+    fn sequence(marker: Marker) -> Self;
+    /// An empty mapping.
+    fn mapping(marker: Marker) -> Self;
+    /// An unexpanded alias; `anchor` returns the name of the anchor.
+    fn alias<F>(id: usize, marker: Marker, anchor: F) -> Self
+    where
+        F: FnOnce() -> String;
+
+    /// Whether the node is a mapping.
+    fn is_mapping(&self) -> bool;
+    /// Whether the node is a sequence.
+    fn is_sequence(&self) -> bool;
+    /// Whether the node is a merge key.
+    fn is_merge_key(&self) -> bool;
+
+    /// Add an item to a sequence.
+    fn push(&mut self, item: Self);
+    /// Add an entry to a mapping.
+    fn insert(&mut self, key: Self, value: Self);
+}
+
+impl LoadedNode for Yaml {
+    fn value(value: Yaml, _: Marker) -> Self {
+        value
+    }
+
+    fn sequence(_: Marker) -> Self {
+        Yaml::Array(Vec::new())
+    }
+
+    fn mapping(_: Marker) -> Self {
+        Yaml::Hash(Hash::new())
+    }
+
+    fn alias<F>(id: usize, _: Marker, _: F) -> Self
+    where
+        F: FnOnce() -> String,
+    {
+        Yaml::Alias(id)
+    }
+
+    fn is_mapping(&self) -> bool {
+        matches!(self, Yaml::Hash(_))
+    }
+
+    fn is_sequence(&self) -> bool {
+        matches!(self, Yaml::Array(_))
+    }
+
+    fn is_merge_key(&self) -> bool {
+        matches!(self, Yaml::String(s) if s == MERGE_KEY)
+    }
+
+    fn push(&mut self, item: Self) {
+        match self {
+            Yaml::Array(arr) => arr.push(item),
+            _ => unreachable!("only sequences have items"),
+        }
+    }
+
+    fn insert(&mut self, key: Self, value: Self) {
+        match self {
+            Yaml::Hash(hash) => {
+                hash.insert(key, value);
+            },
+            _ => unreachable!("only mappings have entries"),
+        }
+    }
+}
+
+impl LoadedNode for Marked {
+    fn value(value: Yaml, marker: Marker) -> Self {
+        Marked {
+            node: MarkedNode::Scalar(value),
+            marker,
+        }
+    }
+
+    fn sequence(marker: Marker) -> Self {
+        Marked {
+            node: MarkedNode::Sequence(Vec::new()),
+            marker,
+        }
+    }
+
+    fn mapping(marker: Marker) -> Self {
+        Marked {
+            node: MarkedNode::Mapping(Vec::new()),
+            marker,
+        }
+    }
+
+    fn alias<F>(id: usize, marker: Marker, anchor: F) -> Self
+    where
+        F: FnOnce() -> String,
+    {
+        Marked {
+            node: MarkedNode::Alias(id, anchor()),
+            marker,
+        }
+    }
+
+    fn is_mapping(&self) -> bool {
+        matches!(self.node, MarkedNode::Mapping(_))
+    }
+
+    fn is_sequence(&self) -> bool {
+        matches!(self.node, MarkedNode::Sequence(_))
+    }
+
+    fn is_merge_key(&self) -> bool {
+        self.is_key(MERGE_KEY)
+    }
+
+    fn push(&mut self, item: Self) {
+        match &mut self.node {
+            MarkedNode::Sequence(items) => items.push(item),
+            _ => unreachable!("only sequences have items"),
+        }
+    }
+
+    fn insert(&mut self, key: Self, value: Self) {
+        match &mut self.node {
+            MarkedNode::Mapping(entries) => entries.push((key, value)),
+            _ => unreachable!("only mappings have entries"),
+        }
+    }
+}
+
+/// A document loader which leaves aliases unexpanded.
+///
+/// This mirrors `yaml_rust::YamlLoader` except that aliases are kept as alias nodes and anchored
+/// nodes are gathered into an `AnchorTable`.
+struct AnchorLoader<'a, N = Yaml> {
+    source: &'a str,
+    /// The byte offset of each character in the source.
+    ///
+    /// Markers count characters, so this is built on demand to find alias names.
+    char_offsets: Option<Vec<usize>>,
+    docs: Vec<N>,
+    // (current node, anchor id) tuple
+    doc_stack: Vec<(N, usize)>,
+    /// The key of the entry being loaded for each mapping on the stack.
+    key_stack: Vec<Option<N>>,
+    anchors: AnchorTable<N>,
+    /// Anchor ids which have been completely parsed.
+    complete: BTreeSet<usize>,
+    /// The positions of merge keys in the current document.
+    merge_keys: Vec<Marker>,
+    /// The positions of merge keys in each document.
+    doc_merge_keys: Vec<Vec<Marker>>,
+    /// The include loader and the path of the file being loaded, if `!include` is supported.
+    includes: Option<(&'a mut Includes, &'a Path)>,
+    /// The first error from an `!include` directive.
+    error: Option<Error>,
+}
+
+/// Whether a character may be used in an anchor name.
+///
+/// This matches the characters that `yaml-rust`'s scanner accepts.
+fn is_anchor_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-'
+}
+
+fn parse_real(v: &str) -> bool {
+    match v {
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => true,
+        "-.inf" | "-.Inf" | "-.INF" => true,
+        ".nan" | "NaN" | ".NAN" => true,
+        _ => v.parse::<f64>().is_ok(),
+    }
+}
+
+impl<'a, N> AnchorLoader<'a, N>
+where
+    N: LoadedNode,
+{
+    fn new(source: &'a str) -> Self {
+        AnchorLoader {
+            source,
+            char_offsets: None,
+            docs: Vec::new(),
+            doc_stack: Vec::new(),
+            key_stack: Vec::new(),
+            anchors: AnchorTable::new(),
+            complete: BTreeSet::new(),
+            merge_keys: Vec::new(),
+            doc_merge_keys: Vec::new(),
+            includes: None,
+            error: None,
+        }
+    }
+
+    /// The name of the anchor used by the alias at the given position.
+    fn alias_name(&mut self, marker: Marker) -> String {
+        let source = self.source;
+        let offsets = self
+            .char_offsets
+            .get_or_insert_with(|| source.char_indices().map(|(idx, _)| idx).collect());
+        let start = offsets.get(marker.index()).copied().unwrap_or(source.len());
+        source[start..]
+            .chars()
+            // Skip the `*` indicator.
+            .skip(1)
+            .take_while(|&c| is_anchor_char(c))
+            .collect()
+    }
+
+    fn scalar(v: String, style: TScalarStyle, tag: Option<TokenType>) -> Yaml {
+        if style != TScalarStyle::Plain {
+            Yaml::String(v)
+        } else if let Some(TokenType::Tag(handle, suffix)) = tag {
+            if handle == "!!" {
+                match suffix.as_ref() {
+                    "bool" => v.parse().map(Yaml::Boolean).unwrap_or(Yaml::BadValue),
+                    "int" => v.parse().map(Yaml::Integer).unwrap_or(Yaml::BadValue),
+                    "float" => {
+                        if parse_real(&v) {
+                            Yaml::Real(v)
+                        } else {
+                            Yaml::BadValue
+                        }
+                    },
+                    "null" => {
+                        match v.as_ref() {
+                            "~" | "null" => Yaml::Null,
+                            _ => Yaml::BadValue,
+                        }
+                    },
+                    _ => Yaml::String(v),
+                }
+            } else {
+                Yaml::String(v)
+            }
+        } else {
+            Yaml::from_str(&v)
+        }
+    }
+
+    /// Whether the next node is a mapping key.
+    fn is_key_position(&self) -> bool {
+        self.doc_stack
+            .last()
+            .map_or(false, |(node, _)| node.is_mapping())
+            && matches!(self.key_stack.last(), Some(None))
+    }
+
+    /// Whether the next node is a merge source.
+    ///
+    /// This is the value of a merge key or an item of a sequence which is the value of a merge
+    /// key.
+    fn is_merge_source_position(&self) -> bool {
+        let is_merge_value =
+            || matches!(self.key_stack.last(), Some(Some(key)) if key.is_merge_key());
+        let len = self.doc_stack.len();
+        match self.doc_stack.last() {
+            Some((node, _)) if node.is_mapping() => is_merge_value(),
+            Some((node, _)) if node.is_sequence() && len >= 2 => {
+                self.doc_stack[len - 2].0.is_mapping() && is_merge_value()
+            },
+            _ => false,
+        }
+    }
+
+    /// Load the document for an `!include` directive.
+    ///
+    /// Errors are stored and a bad value is used instead.
+    fn include(&mut self, target: &str, marker: Marker) -> Yaml {
+        if self.error.is_some() {
+            return Yaml::BadValue;
+        }
+
+        let is_merge_source = self.is_merge_source_position();
+        let (includes, file) = self
+            .includes
+            .as_mut()
+            .expect("includes are only handled with an include loader");
+        let res = if is_merge_source {
+            includes.include(target, file, marker)
+        } else {
+            Err(Error::Include {
+                error: IncludeError::NotMergeSource,
+                chain: vec![includes.site(file, marker)],
+            })
+        };
+
+        res.unwrap_or_else(|err| {
+            self.error = Some(err);
+            Yaml::BadValue
+        })
+    }
+
+    fn insert_new_node(&mut self, node: (N, usize)) {
+        // Valid anchor ids start from 1.
+        if node.1 > 0 {
+            self.anchors.insert(node.1, node.0.clone());
+            self.complete.insert(node.1);
+        }
+
+        if let Some((parent, _)) = self.doc_stack.last_mut() {
+            if parent.is_mapping() {
+                let cur_key = self
+                    .key_stack
+                    .last_mut()
+                    .expect("mappings always have a key slot");
+                if let Some(key) = cur_key.take() {
+                    // The current node is a value.
+                    parent.insert(key, node.0);
+                } else {
+                    // The current node is a key.
+                    *cur_key = Some(node.0);
+                }
+            } else {
+                parent.push(node.0);
+            }
+        } else {
+            self.doc_stack.push(node);
+        }
+    }
+}
+
+impl<'a> AnchorLoader<'a> {
+    fn into_marked_documents(self) -> (MarkedDocuments, AnchorTable) {
+        let docs = self.docs.into_iter().zip(self.doc_merge_keys).collect();
+        (docs, self.anchors)
+    }
+}
+
+impl<'a, N> MarkedEventReceiver for AnchorLoader<'a, N>
+where
+    N: LoadedNode,
+{
+    fn on_event(&mut self, ev: Event, marker: Marker) {
+        match ev {
+            Event::DocumentEnd => {
+                let doc = self
+                    .doc_stack
+                    .pop()
+                    .map(|(doc, _)| doc)
+                    .unwrap_or_else(|| N::value(Yaml::BadValue, marker));
+                self.docs.push(doc);
+                self.doc_merge_keys.push(mem::take(&mut self.merge_keys));
+            },
+            Event::SequenceStart(aid) => {
+                self.doc_stack.push((N::sequence(marker), aid));
+            },
+            Event::MappingStart(aid) => {
+                self.doc_stack.push((N::mapping(marker), aid));
+                self.key_stack.push(None);
+            },
+            Event::SequenceEnd => {
+                let node = self.doc_stack.pop().expect("unbalanced sequence end");
+                self.insert_new_node(node);
+            },
+            Event::MappingEnd => {
+                self.key_stack.pop();
+                let node = self.doc_stack.pop().expect("unbalanced mapping end");
+                self.insert_new_node(node);
+            },
+            Event::Scalar(v, _, aid, Some(TokenType::Tag(handle, suffix)))
+                if self.includes.is_some() && handle == "!" && suffix == INCLUDE_TAG =>
+            {
+                let node = self.include(&v, marker);
+                self.insert_new_node((N::value(node, marker), aid));
+            },
+            Event::Scalar(v, style, aid, tag) => {
+                let node = N::value(Self::scalar(v, style, tag), marker);
+                if self.is_key_position() && node.is_merge_key() {
+                    self.merge_keys.push(marker);
+                }
+                self.insert_new_node((node, aid));
+            },
+            Event::Alias(id) => {
+                if !self.complete.contains(&id) {
+                    let anchor = self.alias_name(marker);
+                    self.anchors.insert_undefined(id, anchor, marker);
+                }
+                let node = N::alias(id, marker, || self.alias_name(marker));
+                self.insert_new_node((node, 0));
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Load YAML documents from a string without expanding aliases.
+///
+/// Aliases are left in the documents as `Yaml::Alias` nodes and the anchored nodes are returned in
+/// an `AnchorTable` (shared by all of the documents) for use with [`merge_keys_with_anchors`].
+/// Aliases to anchors which are not defined at their use (such as an anchored mapping which
+/// refers to itself) are recorded so that merging reports the anchor name and alias position.
+///
+/// [`merge_keys_with_anchors`]: crate::merge_keys_with_anchors
+pub fn load_with_anchors(source: &str) -> Result<(Vec<Yaml>, AnchorTable), ScanError> {
+    let mut loader = AnchorLoader::new(source);
+    let mut parser = Parser::new(source.chars());
+    parser.load(&mut loader, true)?;
+    Ok((loader.docs, loader.anchors))
+}
+
+/// Load YAML documents from a string keeping the position of every node.
+///
+/// As with [`load_with_anchors`], aliases are left unexpanded and anchored nodes are returned in
+/// an `AnchorTable`. This is useful for tools which report where values are written.
+pub fn load_marked(source: &str) -> Result<(Vec<Marked>, AnchorTable<Marked>), ScanError> {
+    let mut loader = AnchorLoader::new(source);
+    let mut parser = Parser::new(source.chars());
+    parser.load(&mut loader, true)?;
+    Ok((loader.docs, loader.anchors))
+}
+
+/// Documents paired with the positions of their merge keys.
+pub(crate) type MarkedDocuments = Vec<(Yaml, Vec<Marker>)>;
+
+/// Load YAML documents from a string without expanding aliases, noting merge key positions.
+///
+/// Each document is returned with the positions of its merge keys in document order.
+pub(crate) fn load_with_merge_key_markers(
+    source: &str,
+) -> Result<(MarkedDocuments, AnchorTable), ScanError> {
+    let mut loader = AnchorLoader::new(source);
+    let mut parser = Parser::new(source.chars());
+    parser.load(&mut loader, true)?;
+    Ok(loader.into_marked_documents())
+}
+
+/// Load YAML documents from a file's contents, handling `!include` directives.
+///
+/// Included documents replace their directives. Merge key positions are noted as with
+/// `load_with_merge_key_markers`.
+pub(crate) fn load_with_includes(
+    source: &str,
+    file: &Path,
+    includes: &mut Includes,
+) -> Result<(MarkedDocuments, AnchorTable), Error> {
+    let mut loader = AnchorLoader::new(source);
+    loader.includes = Some((includes, file));
+    let mut parser = Parser::new(source.chars());
+    parser.load(&mut loader, true)?;
+    if let Some(err) = loader.error.take() {
+        return Err(err);
+    }
+    Ok(loader.into_marked_documents())
+}
@@ -0,0 +1,53 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use yaml_rust::scanner::Marker;
+use yaml_rust::Yaml;
+
+/// A node of a YAML document with its position in the source.
+///
+/// Documents of these nodes are loaded by [`load_marked`].
+///
+/// [`load_marked`]: crate::load_marked
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marked {
+    /// The node.
+    pub node: MarkedNode,
+    /// The position of the node from `yaml-rust`'s parser.
+    ///
+    /// Scalars and aliases are at their start while block collections may be at their first
+    /// indicator.
+    pub marker: Marker,
+}
+
+/// The contents of a [`Marked`] node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkedNode {
+    /// A scalar as resolved by `yaml-rust`.
+    Scalar(Yaml),
+    /// A sequence.
+    Sequence(Vec<Marked>),
+    /// A mapping with its entries in source order.
+    ///
+    /// Unlike `Yaml::Hash`, entries with duplicate keys are kept.
+    Mapping(Vec<(Marked, Marked)>),
+    /// An unexpanded alias with its anchor id and the name of the anchor.
+    Alias(usize, String),
+}
+
+impl Marked {
+    /// Whether the node is a scalar which is written as a given key.
+    ///
+    /// Keys match scalars as they would be resolved or as strings (so `1` matches both `1` and
+    /// `"1"`).
+    pub fn is_key(&self, key: &str) -> bool {
+        match &self.node {
+            MarkedNode::Scalar(Yaml::String(s)) => s == key,
+            MarkedNode::Scalar(scalar) => *scalar == Yaml::from_str(key),
+            _ => false,
+        }
+    }
+}
@@ -4,10 +4,12 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use lazy_static::lazy_static;
 use thiserror::Error;
-use yaml_rust::yaml::{Array, Hash};
-use yaml_rust::Yaml;
+use yaml_rust::scanner::Marker;
+
+use crate::anchors::AnchorTable;
+use crate::node::{MergeableMapping, MergeableNode, NodeKind, MERGE_KEY};
+use crate::options::{MergeOptions, TagPolicy};
 
 /// Errors which may occur when performing the YAML merge key process.
 ///
@@ -27,77 +29,465 @@ pub enum MergeKeyError {
     /// ```
     #[error("only mappings and arrays of mappings may be merged")]
     InvalidMergeValue,
-    // XXX: If adding a new error case, update the test suite to expect the only error case.
+    /// An alias used as a merge value refers to an anchor which is not in the anchor table.
+    ///
+    /// Aliases elsewhere in the document are left as they are when their anchor is not in the
+    /// table.
+    #[error("alias to unknown anchor id {}", _0)]
+    UnresolvedAlias(usize),
+    /// An alias refers to an anchor which has not been defined at that point in the document.
+    ///
+    /// `yaml-rust` replaces such aliases with `Yaml::BadValue`, so the name and position of the
+    /// alias are only available when the document was loaded with [`load_with_anchors`].
+    ///
+    /// This only happens for aliases to an anchor whose node is still being parsed, such as a
+    /// mapping which merges itself. Aliases to anchors which do not appear before them at all
+    /// (such as `<<: *typo`) are rejected by `yaml-rust`'s parser with a `ScanError` ("found
+    /// unknown anchor") before documents are loaded, so they are syntax errors instead.
+    ///
+    /// [`load_with_anchors`]: crate::load_with_anchors
+    #[error("undefined alias{}", describe_alias(.anchor.as_deref(), .marker.as_ref()))]
+    UndefinedAlias {
+        /// The name of the anchor, if known.
+        anchor: Option<String>,
+        /// The position of the alias in the document, if known.
+        marker: Option<Marker>,
+    },
+    /// An anchored node contains an alias to itself.
+    ///
+    /// Expanding such an alias would never terminate.
+    #[error("recursive alias to anchor id {}", _0)]
+    RecursiveAlias(usize),
+    /// A tagged value was used as a merge source with [`TagPolicy::Reject`].
+    ///
+    /// [`TagPolicy::Reject`]: crate::TagPolicy::Reject
+    #[error("tagged value `{}` may not be merged", _0)]
+    TaggedMergeValue(String),
+    /// A merge source has a different tag than the mapping it is merged into with
+    /// [`TagPolicy::RequireMatch`].
+    ///
+    /// [`TagPolicy::RequireMatch`]: crate::TagPolicy::RequireMatch
+    #[error(
+        "merge source tagged `{}` does not match {}",
+        merged,
+        local.as_ref().map_or_else(|| "the untagged mapping".into(), |tag| format!("`{}`", tag))
+    )]
+    TagMismatch {
+        /// The tag of the mapping being merged into, if any.
+        local: Option<String>,
+        /// The tag of the merge source.
+        merged: String,
+    },
 }
 
-lazy_static! {
-    /// The name of the key to use for merge data.
-    static ref MERGE_KEY: Yaml = Yaml::String("<<".into());
+fn describe_alias(anchor: Option<&str>, marker: Option<&Marker>) -> String {
+    let mut desc = String::new();
+    if let Some(anchor) = anchor {
+        desc.push_str(&format!(" `*{}`", anchor));
+    }
+    if let Some(marker) = marker {
+        desc.push_str(&format!(
+            " at line {} column {}",
+            marker.line(),
+            marker.col() + 1
+        ));
+    }
+    desc
+}
+
+impl MergeKeyError {
+    pub(crate) fn undefined_alias() -> Self {
+        MergeKeyError::UndefinedAlias {
+            anchor: None,
+            marker: None,
+        }
+    }
 }
 
-/// Merge two hashes together.
-fn merge_hashes(mut hash: Hash, rhs: Hash) -> Hash {
+/// Merge two mappings together.
+fn merge_mappings<M>(mut mapping: M, rhs: M) -> M
+where
+    M: MergeableMapping,
+{
     rhs.into_iter().for_each(|(key, value)| {
-        hash.entry(key).or_insert(value);
+        if !mapping.contains_key(&key) {
+            mapping.insert(key, value);
+        }
     });
-    hash
+    mapping
+}
+
+/// A step from a node into one of its children.
+///
+/// These are gathered while an error propagates out of the merge to report where it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(not(feature = "serde_yaml"), allow(dead_code))]
+pub(crate) enum PathStep {
+    /// The value for a mapping key (`?` for keys which are nodes themselves).
+    Key(String),
+    /// A sequence item.
+    Index(usize),
 }
 
-/// Merge values together.
-fn merge_values(hash: Hash, value: Yaml) -> Result<Hash, MergeKeyError> {
-    let merge_values = match value {
-        Yaml::Array(arr) => {
-            let init: Result<Hash, _> = Ok(Hash::new());
-
-            arr.into_iter().fold(init, |res_hash, item| {
-                // Merge in the next item.
-                res_hash.and_then(move |res_hash| {
-                    if let Yaml::Hash(next_hash) = item {
-                        Ok(merge_hashes(res_hash, next_hash))
-                    } else {
-                        // Non-hash values at this level are not allowed.
-                        Err(MergeKeyError::InvalidMergeValue)
+/// The outermost tag and structure of a merge source.
+type MergeSource<N> = (Option<<N as MergeableNode>::Tag>, Option<NodeKind<N>>);
+
+/// State for resolving aliases while merging.
+struct Resolver<'a, N>
+where
+    N: MergeableNode,
+{
+    /// The anchors available to aliases.
+    anchors: &'a AnchorTable<N>,
+    /// Options for merging.
+    options: &'a MergeOptions,
+    /// The anchors currently being expanded.
+    expanding: Vec<usize>,
+    /// Describes mapping keys for error paths; paths are only gathered when this is set.
+    key_name: Option<fn(&N::Key) -> String>,
+    /// The path to the node which failed to merge, innermost step first.
+    error_path: Vec<PathStep>,
+}
+
+impl<'a, N> Resolver<'a, N>
+where
+    N: MergeableNode + Clone,
+{
+    fn new(anchors: &'a AnchorTable<N>, options: &'a MergeOptions) -> Self {
+        Resolver {
+            anchors,
+            options,
+            expanding: Vec::new(),
+            key_name: None,
+            error_path: Vec::new(),
+        }
+    }
+
+    /// Note that an error happened within a child of the current node.
+    fn note_step<F>(&mut self, step: F)
+    where
+        F: FnOnce(fn(&N::Key) -> String) -> PathStep,
+    {
+        if let Some(key_name) = self.key_name {
+            self.error_path.push(step(key_name));
+        }
+    }
+
+    /// Handle merge keys in a node.
+    fn merge_keys(&mut self, doc: N) -> Result<N, MergeKeyError> {
+        let (kind, meta) = match doc.into_parts() {
+            Ok(parts) => parts,
+            Err(doc) => return Ok(doc),
+        };
+
+        let kind = match kind {
+            NodeKind::Mapping(mapping) => {
+                match self.merge_mapping(mapping, None)? {
+                    (mapping, None) => NodeKind::Mapping(mapping),
+                    (mapping, Some(tag)) => {
+                        let mapping = N::from_parts(NodeKind::Mapping(mapping), meta.clone());
+                        NodeKind::Tagged(tag, Box::new(mapping))
+                    },
+                }
+            },
+            NodeKind::Sequence(seq) => NodeKind::Sequence(self.merge_sequence(seq)?),
+            NodeKind::Tagged(tag, value) => self.merge_tagged(tag, *value)?,
+            NodeKind::Alias(id) => return self.resolve_alias(id, meta),
+            NodeKind::Undefined => NodeKind::Undefined,
+        };
+
+        Ok(N::from_parts(kind, meta))
+    }
+
+    /// Expand an alias and handle merge keys in the anchored node.
+    ///
+    /// Aliases to anchors which are not in the table are kept; they are only an error when used
+    /// as a merge value.
+    fn resolve_alias(&mut self, id: usize, meta: N::Meta) -> Result<N, MergeKeyError> {
+        if let Some((anchor, marker)) = self.anchors.undefined(id) {
+            return Err(MergeKeyError::UndefinedAlias {
+                anchor: Some(anchor.into()),
+                marker: Some(marker),
+            });
+        }
+        if self.expanding.contains(&id) {
+            return Err(MergeKeyError::RecursiveAlias(id));
+        }
+        let node = match self.anchors.get(id) {
+            Some(node) => node.clone(),
+            None => return Ok(N::from_parts(NodeKind::Alias(id), meta)),
+        };
+
+        self.expanding.push(id);
+        let res = self.merge_keys(node);
+        self.expanding.pop();
+
+        res
+    }
+
+    /// Handle merge keys in a tagged node.
+    fn merge_tagged(&mut self, tag: N::Tag, value: N) -> Result<NodeKind<N>, MergeKeyError> {
+        let value = match value.into_parts() {
+            Ok((NodeKind::Mapping(mapping), meta)) => {
+                let (mapping, _) = self.merge_mapping(mapping, Some(&tag))?;
+                N::from_parts(NodeKind::Mapping(mapping), meta)
+            },
+            Ok((kind, meta)) => self.merge_keys(N::from_parts(kind, meta))?,
+            Err(scalar) => scalar,
+        };
+
+        Ok(NodeKind::Tagged(tag, Box::new(value)))
+    }
+
+    /// Rebuild a mapping with merge keys handled in its keys and values.
+    ///
+    /// Returns the mapping without its merge entry and the value of the merge entry, if any.
+    fn rebuild_mapping(
+        &mut self,
+        mapping: N::Mapping,
+    ) -> Result<(N::Mapping, Option<N>), MergeKeyError> {
+        let mut merge_value = None;
+        let mapping = mapping
+            .into_iter()
+            // Set aside the merge value rather than removing it afterwards; removal from some
+            // mapping types reorders the remaining keys.
+            .filter_map(|(key, value)| {
+                if N::is_merge_key(&key) {
+                    merge_value = Some(value);
+                    None
+                } else {
+                    Some((key, value))
+                }
+            })
+            // First handle any merge keys in the key or value...
+            .map(|(key, value)| {
+                let key = N::merge_in_key(key, |key| self.merge_keys(key)).map_err(|err| {
+                    self.note_step(|_| PathStep::Key("?".into()));
+                    err
+                })?;
+                let value = self.merge_keys(value).map_err(|err| {
+                    self.note_step(|key_name| PathStep::Key(key_name(&key)));
+                    err
+                })?;
+                Ok((key, value))
+            })
+            .collect::<Result<N::Mapping, _>>()?;
+
+        Ok((mapping, merge_value))
+    }
+
+    /// Recurse into a mapping and handle items with merge keys in them.
+    ///
+    /// Returns the merged mapping and the tag it should inherit from its merge sources, if any.
+    fn merge_mapping(
+        &mut self,
+        mut mapping: N::Mapping,
+        local_tag: Option<&N::Tag>,
+    ) -> Result<(N::Mapping, Option<N::Tag>), MergeKeyError> {
+        let in_place = mapping.merge_in_place(N::is_merge_key, |key, value| {
+            self.merge_keys(value).map_err(|err| {
+                self.note_step(|key_name| PathStep::Key(key_name(key)));
+                err
+            })
+        });
+        let merge_value = match in_place {
+            Some(res) => res?,
+            None => {
+                let (rebuilt, merge_value) = self.rebuild_mapping(mapping)?;
+                mapping = rebuilt;
+                merge_value
+            },
+        };
+
+        if let Some(merge_value) = merge_value {
+            let merge_value = self.merge_keys(merge_value).map_err(|err| {
+                self.note_step(|_| PathStep::Key(MERGE_KEY.into()));
+                err
+            })?;
+            let (mapping, source_tag) = self.merge_values(mapping, merge_value, local_tag)?;
+            let inherited_tag = match self.options.tag_policy {
+                // A local tag takes precedence just like local keys do.
+                TagPolicy::InheritFirst if local_tag.is_none() => source_tag,
+                _ => None,
+            };
+            Ok((mapping, inherited_tag))
+        } else {
+            Ok((mapping, None))
+        }
+    }
+
+    /// Check the tag of a merge source against the tag policy.
+    fn check_source_tag(
+        &self,
+        local_tag: Option<&N::Tag>,
+        source_tag: &N::Tag,
+    ) -> Result<(), MergeKeyError> {
+        match self.options.tag_policy {
+            TagPolicy::KeepLocal | TagPolicy::InheritFirst => Ok(()),
+            TagPolicy::Reject => Err(MergeKeyError::TaggedMergeValue(source_tag.to_string())),
+            TagPolicy::RequireMatch => {
+                if local_tag == Some(source_tag) {
+                    Ok(())
+                } else {
+                    Err(MergeKeyError::TagMismatch {
+                        local: local_tag.map(ToString::to_string),
+                        merged: source_tag.to_string(),
+                    })
+                }
+            },
+        }
+    }
+
+    /// Get the structure and tag of a merge source with any tags removed.
+    ///
+    /// Returns no structure for scalar values.
+    fn merge_source(
+        &self,
+        mut value: N,
+        local_tag: Option<&N::Tag>,
+    ) -> Result<MergeSource<N>, MergeKeyError> {
+        let mut source_tag = None;
+        loop {
+            match value.into_parts() {
+                Ok((NodeKind::Tagged(tag, inner), _)) => {
+                    self.check_source_tag(local_tag, &tag)?;
+                    source_tag.get_or_insert(tag);
+                    value = *inner;
+                },
+                Ok((kind, _)) => return Ok((source_tag, Some(kind))),
+                Err(_) => return Ok((source_tag, None)),
+            }
+        }
+    }
+
+    /// Merge values together.
+    ///
+    /// Returns the merged mapping and the tag of the first merge source.
+    fn merge_values(
+        &self,
+        mut mapping: N::Mapping,
+        value: N,
+        local_tag: Option<&N::Tag>,
+    ) -> Result<(N::Mapping, Option<N::Tag>), MergeKeyError> {
+        match self.merge_source(value, local_tag)? {
+            (_, Some(NodeKind::Sequence(seq))) => {
+                // Earlier merge sources take precedence, so each one may be merged directly into
+                // the mapping in order.
+                let mut first_tag = None;
+                for (idx, item) in seq.into_iter().enumerate() {
+                    // Merge in the next item.
+                    match self.merge_source(item, local_tag)? {
+                        (tag, Some(NodeKind::Mapping(next_mapping))) => {
+                            if idx == 0 {
+                                first_tag = tag;
+                            }
+                            mapping = merge_mappings(mapping, next_mapping);
+                        },
+                        (_, Some(NodeKind::Undefined)) => {
+                            return Err(MergeKeyError::undefined_alias());
+                        },
+                        (_, Some(NodeKind::Alias(id))) => {
+                            return Err(MergeKeyError::UnresolvedAlias(id));
+                        },
+                        // Non-mapping values at this level are not allowed.
+                        _ => return Err(MergeKeyError::InvalidMergeValue),
                     }
+                }
+                Ok((mapping, first_tag))
+            },
+            (tag, Some(NodeKind::Mapping(merge_mapping))) => {
+                Ok((merge_mappings(mapping, merge_mapping), tag))
+            },
+            (_, Some(NodeKind::Undefined)) => Err(MergeKeyError::undefined_alias()),
+            (_, Some(NodeKind::Alias(id))) => Err(MergeKeyError::UnresolvedAlias(id)),
+            _ => Err(MergeKeyError::InvalidMergeValue),
+        }
+    }
+
+    /// Recurse into a sequence and handle items with merge keys in them.
+    fn merge_sequence(&mut self, seq: Vec<N>) -> Result<Vec<N>, MergeKeyError> {
+        seq.into_iter()
+            .enumerate()
+            .map(|(idx, item)| {
+                self.merge_keys(item).map_err(|err| {
+                    self.note_step(|_| PathStep::Index(idx));
+                    err
                 })
-            })?
-        },
-        Yaml::Hash(merge_hash) => merge_hash,
-        _ => return Err(MergeKeyError::InvalidMergeValue),
-    };
+            })
+            .collect()
+    }
+}
 
-    Ok(merge_hashes(hash, merge_values))
+/// Handle merge keys in a YAML document.
+pub fn merge_keys<N>(doc: N) -> Result<N, MergeKeyError>
+where
+    N: MergeableNode + Clone,
+{
+    merge_keys_with_anchors(doc, &AnchorTable::new())
 }
 
-/// Recurse into a hash and handle items with merge keys in them.
-fn merge_hash(hash: Hash) -> Result<Yaml, MergeKeyError> {
-    let mut hash = hash
-        .into_iter()
-        // First handle any merge keys in the key or value...
-        .map(|(key, value)| {
-            merge_keys(key).and_then(|key| merge_keys(value).map(|value| (key, value)))
-        })
-        .collect::<Result<Hash, _>>()?;
-
-    if let Some(merge_value) = hash.remove(&MERGE_KEY) {
-        merge_values(hash, merge_value).map(Yaml::Hash)
-    } else {
-        Ok(Yaml::Hash(hash))
-    }
+/// Handle merge keys in a YAML document which may contain unexpanded aliases.
+///
+/// Any alias node in the document is replaced by its anchored node from `anchors` (with merge
+/// keys handled) before merging. This includes aliases used as merge values. Aliases to anchors
+/// missing from `anchors` are kept unless they are used as merge values, which is an error.
+pub fn merge_keys_with_anchors<N>(doc: N, anchors: &AnchorTable<N>) -> Result<N, MergeKeyError>
+where
+    N: MergeableNode + Clone,
+{
+    merge_keys_with_options(doc, anchors, &MergeOptions::default())
 }
 
-/// Recurse into an array and handle items with merge keys in them.
-fn merge_array(arr: Array) -> Result<Yaml, MergeKeyError> {
-    arr.into_iter()
-        .map(merge_keys)
-        .collect::<Result<Array, _>>()
-        .map(Yaml::Array)
+/// Handle merge keys in a YAML document with options.
+///
+/// Use an empty [`AnchorTable`] for documents without unexpanded aliases.
+pub fn merge_keys_with_options<N>(
+    doc: N,
+    anchors: &AnchorTable<N>,
+    options: &MergeOptions,
+) -> Result<N, MergeKeyError>
+where
+    N: MergeableNode + Clone,
+{
+    Resolver::new(anchors, options).merge_keys(doc)
 }
 
-/// Handle merge keys in a YAML document.
-pub fn merge_keys(doc: Yaml) -> Result<Yaml, MergeKeyError> {
-    match doc {
-        Yaml::Hash(hash) => merge_hash(hash),
-        Yaml::Array(arr) => merge_array(arr),
-        _ => Ok(doc),
-    }
+/// Handle merge keys in a document, returning the path to the failing node with errors.
+///
+/// `key_name` describes mapping keys for the path.
+#[cfg(feature = "serde_yaml")]
+pub(crate) fn merge_keys_located<N>(
+    doc: N,
+    options: &MergeOptions,
+    key_name: fn(&N::Key) -> String,
+) -> Result<N, (MergeKeyError, Vec<PathStep>)>
+where
+    N: MergeableNode + Clone,
+{
+    let anchors = AnchorTable::new();
+    let mut resolver = Resolver::new(&anchors, options);
+    resolver.key_name = Some(key_name);
+    resolver.merge_keys(doc).map_err(|err| {
+        let mut path = resolver.error_path;
+        path.reverse();
+        (err, path)
+    })
+}
+
+/// Merge an already-merged merge value into a mapping.
+///
+/// The merge value must not contain merge keys or aliases itself.
+#[cfg(feature = "serde_yaml")]
+pub(crate) fn merge_into_mapping<N>(
+    mapping: N::Mapping,
+    merge_value: N,
+) -> Result<N::Mapping, MergeKeyError>
+where
+    N: MergeableNode + Clone,
+{
+    let anchors = AnchorTable::new();
+    let options = MergeOptions::default();
+    Resolver::new(&anchors, &options)
+        .merge_values(mapping, merge_value, None)
+        .map(|(mapping, _)| mapping)
 }
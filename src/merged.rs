@@ -0,0 +1,160 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use serde::de::{self, Deserialize, DeserializeOwned, Deserializer};
+use serde::ser::{Serialize, Serializer};
+use serde_yaml::Value;
+
+use crate::merge_keys::{merge_keys_located, PathStep};
+use crate::{MergeKeyError, MergeOptions};
+
+/// A value which has had merge keys handled before being deserialized.
+///
+/// This is useful for YAML-shaped data embedded in a larger structure:
+///
+/// ```rust
+/// # extern crate serde;
+/// # #[cfg(feature = "serde_json")]
+/// # extern crate serde_json;
+/// # extern crate yaml_merge_keys;
+/// # #[cfg(feature = "serde_json")]
+/// # fn main() {
+/// use std::collections::BTreeMap;
+///
+/// use serde::Deserialize;
+/// use yaml_merge_keys::Merged;
+///
+/// #[derive(Deserialize)]
+/// struct Payload {
+///     config: Merged<BTreeMap<String, BTreeMap<String, u32>>>,
+/// }
+///
+/// let raw = r#"{"config": {"base": {"a": 1}, "dict": {"<<": {"a": 1}, "b": 2}}}"#;
+/// let payload: Payload = serde_json::from_str(raw).unwrap();
+///
+/// assert_eq!(payload.config["dict"]["a"], 1);
+/// # }
+/// # #[cfg(not(feature = "serde_json"))]
+/// # fn main() {}
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Merged<T>(pub T);
+
+impl<T> Merged<T> {
+    /// Unwrap the value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Merged<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Merged<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Merged<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserialize_merged(deserializer).map(Merged)
+    }
+}
+
+impl<T> Serialize for Merged<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+/// Deserialize a value after handling its merge keys.
+///
+/// Intended for use with `#[serde(deserialize_with = "yaml_merge_keys::deserialize_merged")]`.
+/// Errors from handling merge keys include the path to the mapping within the value.
+pub fn deserialize_merged<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    deserialize_merged_with_options(deserializer, &MergeOptions::default())
+}
+
+/// Deserialize a value after handling its merge keys with options.
+///
+/// Use a function which passes the options along with `deserialize_with`. Errors from handling
+/// merge keys include the path to the mapping within the value.
+pub fn deserialize_merged_with_options<'de, D, T>(
+    deserializer: D,
+    options: &MergeOptions,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let value = Value::deserialize(deserializer)?;
+    let merged = merge_keys_located(value, options, key_name).map_err(|(error, path)| {
+        de::Error::custom(PathError {
+            path,
+            error,
+        })
+    })?;
+    T::deserialize(merged).map_err(de::Error::custom)
+}
+
+/// Describe a mapping key in an error path.
+fn key_name(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::Null => "~".into(),
+        _ => "?".into(),
+    }
+}
+
+/// A merge key error at a location within a value.
+struct PathError {
+    path: Vec<PathStep>,
+    error: MergeKeyError,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at `", self.error)?;
+        for (idx, step) in self.path.iter().enumerate() {
+            match step {
+                PathStep::Key(key) if idx == 0 => write!(f, "{}", key)?,
+                PathStep::Key(key) => write!(f, ".{}", key)?,
+                PathStep::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        if self.path.is_empty() {
+            write!(f, ".")?;
+        }
+        write!(f, "`")
+    }
+}
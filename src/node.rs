@@ -0,0 +1,182 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::convert::Infallible;
+use std::fmt;
+use std::iter::FromIterator;
+use std::mem;
+
+use yaml_rust::yaml::Hash;
+use yaml_rust::Yaml;
+
+/// The name of the key to use for merge data.
+pub const MERGE_KEY: &str = "<<";
+
+/// The structure of a document node as seen by the merge process.
+pub enum NodeKind<N>
+where
+    N: MergeableNode,
+{
+    /// A mapping.
+    Mapping(N::Mapping),
+    /// A sequence.
+    Sequence(Vec<N>),
+    /// A node with a tag applied to it.
+    Tagged(N::Tag, Box<N>),
+    /// An unexpanded alias to an anchor id.
+    Alias(usize),
+    /// A placeholder left behind by the loader for an alias to an undefined anchor.
+    Undefined,
+}
+
+/// A mapping within a document which may be merged into.
+pub trait MergeableMapping:
+    Default + IntoIterator<Item = (Self::Key, Self::Value)> + FromIterator<(Self::Key, Self::Value)>
+{
+    /// The type of keys in the mapping.
+    type Key;
+    /// The type of values in the mapping.
+    type Value;
+
+    /// Whether the mapping has an entry with an equal key or not.
+    fn contains_key(&self, key: &Self::Key) -> bool;
+    /// Add an entry to the end of the mapping.
+    ///
+    /// This is only called for keys which are not already in the mapping.
+    fn insert(&mut self, key: Self::Key, value: Self::Value);
+
+    /// Handle merge keys within the values of the mapping without rebuilding it.
+    ///
+    /// The merge entry (as identified by `is_merge_key`) is removed without reordering the other
+    /// entries and its value is returned. Every other value is replaced by the result of `merge`,
+    /// which is given the key of the entry as well.
+    ///
+    /// Mappings which cannot be updated in place (or which have keys that may contain merge keys
+    /// themselves) return `None` and are rebuilt instead, which is what the default
+    /// implementation does.
+    fn merge_in_place<E, F>(
+        &mut self,
+        _is_merge_key: fn(&Self::Key) -> bool,
+        _merge: F,
+    ) -> Option<Result<Option<Self::Value>, E>>
+    where
+        F: FnMut(&Self::Key, Self::Value) -> Result<Self::Value, E>,
+    {
+        None
+    }
+}
+
+/// A document node type which supports merge keys.
+///
+/// Implementing this trait allows [`merge_keys`] to be used with the type.
+///
+/// [`merge_keys`]: crate::merge_keys
+pub trait MergeableNode: Sized {
+    /// The type of keys in mappings.
+    type Key;
+    /// The type of mappings.
+    type Mapping: MergeableMapping<Key = Self::Key, Value = Self>;
+    /// The type of tags (use `std::convert::Infallible` if tags are not supported).
+    type Tag: fmt::Display + PartialEq;
+    /// Data which is kept aside while the contents of a node are merged (such as a source span).
+    type Meta: Clone;
+
+    /// Split a node into its structure and metadata.
+    ///
+    /// Nodes which cannot contain merge keys (such as scalars) are returned as-is in `Err`.
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self>;
+    /// Rebuild a node from its structure and metadata.
+    fn from_parts(kind: NodeKind<Self>, meta: Self::Meta) -> Self;
+    /// Whether a mapping key is the merge key or not.
+    fn is_merge_key(key: &Self::Key) -> bool;
+    /// Handle merge keys within a mapping key.
+    ///
+    /// The `merge` function handles merge keys in a node. Key types which are not nodes (and
+    /// therefore cannot contain merge keys) may return the key as-is.
+    fn merge_in_key<E, F>(key: Self::Key, merge: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>;
+}
+
+impl MergeableMapping for Hash {
+    type Key = Yaml;
+    type Value = Yaml;
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        Hash::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) {
+        Hash::insert(self, key, value);
+    }
+
+    fn merge_in_place<E, F>(
+        &mut self,
+        is_merge_key: fn(&Self::Key) -> bool,
+        mut merge: F,
+    ) -> Option<Result<Option<Self::Value>, E>>
+    where
+        F: FnMut(&Self::Key, Self::Value) -> Result<Self::Value, E>,
+    {
+        let is_node = |key: &Yaml| {
+            matches!(
+                key,
+                Yaml::Hash(_) | Yaml::Array(_) | Yaml::Alias(_) | Yaml::BadValue,
+            )
+        };
+        if self.keys().any(is_node) {
+            return None;
+        }
+
+        // Only `Yaml::String` keys are merge keys, so there is at most one.
+        let merge_key = self.keys().find(|key| is_merge_key(key)).cloned();
+        let merge_value = merge_key.and_then(|key| self.remove(&key));
+        let res = self.iter_mut().try_for_each(|(key, value)| {
+            *value = merge(key, mem::replace(value, Yaml::Null))?;
+            Ok(())
+        });
+        Some(res.map(|()| merge_value))
+    }
+}
+
+impl MergeableNode for Yaml {
+    type Key = Yaml;
+    type Mapping = Hash;
+    type Tag = Infallible;
+    type Meta = ();
+
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self> {
+        let kind = match self {
+            Yaml::Hash(hash) => NodeKind::Mapping(hash),
+            Yaml::Array(arr) => NodeKind::Sequence(arr),
+            Yaml::Alias(id) => NodeKind::Alias(id),
+            Yaml::BadValue => NodeKind::Undefined,
+            _ => return Err(self),
+        };
+        Ok((kind, ()))
+    }
+
+    fn from_parts(kind: NodeKind<Self>, _: Self::Meta) -> Self {
+        match kind {
+            NodeKind::Mapping(hash) => Yaml::Hash(hash),
+            NodeKind::Sequence(arr) => Yaml::Array(arr),
+            NodeKind::Tagged(tag, _) => match tag {},
+            NodeKind::Alias(id) => Yaml::Alias(id),
+            NodeKind::Undefined => Yaml::BadValue,
+        }
+    }
+
+    fn is_merge_key(key: &Self::Key) -> bool {
+        matches!(key, Yaml::String(s) if s == MERGE_KEY)
+    }
+
+    fn merge_in_key<E, F>(key: Self::Key, merge: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>,
+    {
+        merge(key)
+    }
+}
@@ -0,0 +1,65 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// How tags are handled when a tagged value is used as a merge source.
+///
+/// For example, with a document such as:
+///
+/// ```yaml
+/// base: &base !Custom
+///   a: 1
+/// dict:
+///   <<: *base
+///   b: 2
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TagPolicy {
+    /// Merge the contents of tagged merge sources and keep the tag of the merging mapping.
+    ///
+    /// The tags of merge sources are dropped.
+    ///
+    /// This is the default.
+    KeepLocal,
+    /// Report an error for tagged merge sources.
+    Reject,
+    /// Untagged mappings inherit the tag of their first merge source.
+    ///
+    /// Mappings which have a tag keep it, just as their keys take precedence over merged keys.
+    InheritFirst,
+    /// Report an error for merge sources with a different tag than the mapping they are merged
+    /// into.
+    ///
+    /// Untagged merge sources are always accepted.
+    RequireMatch,
+}
+
+impl Default for TagPolicy {
+    fn default() -> Self {
+        TagPolicy::KeepLocal
+    }
+}
+
+/// Options for the merge key process.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct MergeOptions {
+    /// How tags on merge sources are handled.
+    pub tag_policy: TagPolicy,
+}
+
+impl MergeOptions {
+    /// The default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how tags on merge sources are handled.
+    pub fn with_tag_policy(mut self, tag_policy: TagPolicy) -> Self {
+        self.tag_policy = tag_policy;
+        self
+    }
+}
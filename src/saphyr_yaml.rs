@@ -0,0 +1,235 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::borrow::Cow;
+use std::hash::Hash;
+use std::mem;
+
+use saphyr::{AnnotatedMapping, MarkedYaml, Scalar, Tag, Yaml, YamlData};
+
+use crate::node::{MergeableMapping, MergeableNode, NodeKind, MERGE_KEY};
+use crate::MergeKeyError;
+
+fn is_merge_key_scalar(data: &Scalar) -> bool {
+    matches!(data, Scalar::String(s) if s == MERGE_KEY)
+}
+
+/// Handle merge keys in the values of a mapping in place.
+///
+/// `placeholder` creates a node to leave behind while a value is being merged.
+fn merge_in_place<Node, E, F, P>(
+    mapping: &mut AnnotatedMapping<Node>,
+    is_merge_key: fn(&Node) -> bool,
+    mut merge: F,
+    placeholder: P,
+) -> Result<Option<Node>, E>
+where
+    Node: Hash + Eq,
+    F: FnMut(&Node, Node) -> Result<Node, E>,
+    P: Fn(&Node) -> Node,
+{
+    // Both the plain and the representation forms of `<<` are merge keys; the last one wins as
+    // when the mapping is rebuilt.
+    let mut merge_value = None;
+    mapping.retain(|key, value| {
+        if is_merge_key(key) {
+            merge_value = Some(mem::replace(value, placeholder(value)));
+            false
+        } else {
+            true
+        }
+    });
+    mapping.iter_mut().try_for_each(|(key, value)| {
+        *value = merge(key, mem::replace(value, placeholder(value)))?;
+        Ok(())
+    })?;
+    Ok(merge_value)
+}
+
+// The resolver only inserts keys which are not yet present, so `AnnotatedMapping::insert` keeps
+// the order of the mapping's own entries.
+impl<'input> MergeableMapping for AnnotatedMapping<'input, Yaml<'input>> {
+    type Key = Yaml<'input>;
+    type Value = Yaml<'input>;
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        AnnotatedMapping::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) {
+        AnnotatedMapping::insert(self, key, value);
+    }
+
+    fn merge_in_place<E, F>(
+        &mut self,
+        is_merge_key: fn(&Self::Key) -> bool,
+        merge: F,
+    ) -> Option<Result<Option<Self::Value>, E>>
+    where
+        F: FnMut(&Self::Key, Self::Value) -> Result<Self::Value, E>,
+    {
+        // Keys which are nodes may change when their merge keys are handled.
+        let is_scalar = |key: &Yaml| matches!(key, Yaml::Value(_) | Yaml::Representation(..));
+        if !self.keys().all(is_scalar) {
+            return None;
+        }
+        Some(merge_in_place(self, is_merge_key, merge, |_| {
+            Yaml::BadValue
+        }))
+    }
+}
+
+impl<'input> MergeableMapping for AnnotatedMapping<'input, MarkedYaml<'input>> {
+    type Key = MarkedYaml<'input>;
+    type Value = MarkedYaml<'input>;
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        AnnotatedMapping::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) {
+        AnnotatedMapping::insert(self, key, value);
+    }
+
+    fn merge_in_place<E, F>(
+        &mut self,
+        is_merge_key: fn(&Self::Key) -> bool,
+        merge: F,
+    ) -> Option<Result<Option<Self::Value>, E>>
+    where
+        F: FnMut(&Self::Key, Self::Value) -> Result<Self::Value, E>,
+    {
+        let is_scalar = |key: &MarkedYaml| {
+            matches!(key.data, YamlData::Value(_) | YamlData::Representation(..))
+        };
+        if !self.keys().all(is_scalar) {
+            return None;
+        }
+        // Placeholders keep the span of the value they stand in for.
+        let placeholder = |value: &MarkedYaml<'input>| {
+            MarkedYaml {
+                span: value.span,
+                data: YamlData::BadValue,
+            }
+        };
+        Some(merge_in_place(self, is_merge_key, merge, placeholder))
+    }
+}
+
+impl<'input> MergeableNode for Yaml<'input> {
+    type Key = Self;
+    type Mapping = AnnotatedMapping<'input, Self>;
+    type Tag = Cow<'input, Tag>;
+    type Meta = ();
+
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self> {
+        let kind = match self {
+            Yaml::Mapping(mapping) => NodeKind::Mapping(mapping),
+            Yaml::Sequence(sequence) => NodeKind::Sequence(sequence),
+            Yaml::Tagged(tag, value) => NodeKind::Tagged(tag, value),
+            Yaml::Alias(id) => NodeKind::Alias(id),
+            Yaml::BadValue => NodeKind::Undefined,
+            scalar => return Err(scalar),
+        };
+        Ok((kind, ()))
+    }
+
+    fn from_parts(kind: NodeKind<Self>, _: Self::Meta) -> Self {
+        match kind {
+            NodeKind::Mapping(mapping) => Yaml::Mapping(mapping),
+            NodeKind::Sequence(sequence) => Yaml::Sequence(sequence),
+            NodeKind::Tagged(tag, value) => Yaml::Tagged(tag, value),
+            NodeKind::Alias(id) => Yaml::Alias(id),
+            NodeKind::Undefined => Yaml::BadValue,
+        }
+    }
+
+    fn is_merge_key(key: &Self::Key) -> bool {
+        match key {
+            Yaml::Value(value) => is_merge_key_scalar(value),
+            Yaml::Representation(repr, _, None) => repr == MERGE_KEY,
+            _ => false,
+        }
+    }
+
+    fn merge_in_key<E, F>(key: Self::Key, merge: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>,
+    {
+        merge(key)
+    }
+}
+
+impl<'input> MergeableNode for MarkedYaml<'input> {
+    type Key = Self;
+    type Mapping = AnnotatedMapping<'input, Self>;
+    type Tag = Cow<'input, Tag>;
+    /// The span of the node, kept in an empty node.
+    type Meta = Self;
+
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self> {
+        let kind = match self.data {
+            YamlData::Mapping(mapping) => NodeKind::Mapping(mapping),
+            YamlData::Sequence(sequence) => NodeKind::Sequence(sequence),
+            YamlData::Tagged(tag, value) => NodeKind::Tagged(tag, value),
+            YamlData::Alias(id) => NodeKind::Alias(id),
+            YamlData::BadValue => NodeKind::Undefined,
+            data => {
+                return Err(MarkedYaml {
+                    span: self.span,
+                    data,
+                })
+            },
+        };
+        let meta = MarkedYaml {
+            span: self.span,
+            data: YamlData::BadValue,
+        };
+        Ok((kind, meta))
+    }
+
+    fn from_parts(kind: NodeKind<Self>, mut meta: Self::Meta) -> Self {
+        meta.data = match kind {
+            NodeKind::Mapping(mapping) => YamlData::Mapping(mapping),
+            NodeKind::Sequence(sequence) => YamlData::Sequence(sequence),
+            NodeKind::Tagged(tag, value) => YamlData::Tagged(tag, value),
+            NodeKind::Alias(id) => YamlData::Alias(id),
+            NodeKind::Undefined => YamlData::BadValue,
+        };
+        meta
+    }
+
+    fn is_merge_key(key: &Self::Key) -> bool {
+        match &key.data {
+            YamlData::Value(value) => is_merge_key_scalar(value),
+            YamlData::Representation(repr, _, None) => repr == MERGE_KEY,
+            _ => false,
+        }
+    }
+
+    fn merge_in_key<E, F>(key: Self::Key, merge: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>,
+    {
+        merge(key)
+    }
+}
+
+/// Handle merge keys in a `saphyr` YAML document.
+///
+/// Aliases are expected to have been expanded by the loader; any remaining `Yaml::Alias` node is
+/// reported as an unresolved alias.
+pub fn merge_keys_saphyr(doc: Yaml) -> Result<Yaml, MergeKeyError> {
+    crate::merge_keys(doc)
+}
+
+/// Handle merge keys in a `saphyr` YAML document with source spans.
+///
+/// Merged entries keep the spans of their source mapping while the merged mapping keeps its own
+/// span.
+pub fn merge_keys_saphyr_marked(doc: MarkedYaml) -> Result<MarkedYaml, MergeKeyError> {
+    crate::merge_keys(doc)
+}
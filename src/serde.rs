@@ -4,164 +4,150 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use serde_yaml::value::{Tag, TaggedValue};
-use serde_yaml::Value;
-use yaml_rust::yaml::Hash;
-use yaml_rust::Yaml;
+/// Implement merging for a `serde_yaml`-compatible `Value` type.
+///
+/// The forks of `serde_yaml` share its `Value` API, so the same implementation is used for all of
+/// them.
+macro_rules! serde_yaml_value {
+    ( $krate:ident, $merge_keys:ident, $merge_keys_with_options:ident $(,)? ) => {
+        impl crate::MergeableMapping for $krate::Mapping {
+            type Key = $krate::Value;
+            type Value = $krate::Value;
 
-use crate::merge_keys;
-use crate::MergeKeyError;
+            fn contains_key(&self, key: &Self::Key) -> bool {
+                $krate::Mapping::contains_key(self, key)
+            }
 
-struct YamlWrap(Yaml);
+            fn insert(&mut self, key: Self::Key, value: Self::Value) {
+                $krate::Mapping::insert(self, key, value);
+            }
 
-impl YamlWrap {
-    fn into_yaml(self) -> Yaml {
-        self.into()
-    }
-}
+            fn merge_in_place<E, F>(
+                &mut self,
+                is_merge_key: fn(&Self::Key) -> bool,
+                mut merge: F,
+            ) -> Option<Result<Option<Self::Value>, E>>
+            where
+                F: FnMut(&Self::Key, Self::Value) -> Result<Self::Value, E>,
+            {
+                use $krate::Value;
 
-impl From<YamlWrap> for Yaml {
-    fn from(yaml: YamlWrap) -> Self {
-        yaml.0
-    }
-}
+                let is_node = |key: &Value| {
+                    matches!(key, Value::Mapping(_) | Value::Sequence(_) | Value::Tagged(_))
+                };
+                if self.keys().any(is_node) {
+                    return None;
+                }
 
-impl From<Yaml> for YamlWrap {
-    fn from(yaml: Yaml) -> Self {
-        YamlWrap(yaml)
-    }
-}
+                let mut merge_value = None;
+                // `retain` keeps the order of the remaining entries (unlike `remove`).
+                self.retain(|key, value| {
+                    if is_merge_key(key) {
+                        merge_value = Some(std::mem::replace(value, Value::Null));
+                        false
+                    } else {
+                        true
+                    }
+                });
+                let res = self.iter_mut().try_for_each(|(key, value)| {
+                    *value = merge(key, std::mem::replace(value, Value::Null))?;
+                    Ok(())
+                });
+                Some(res.map(|()| merge_value))
+            }
+        }
 
-pub(crate) const TAGGED_YAML_SMUGGLE_TAG_KEY: &str = "70235535-46bb-46f9-b535-31596b77733f";
-pub(crate) const TAGGED_YAML_SMUGGLE_VALUE_KEY: &str = "b5282e53-aaaf-4f67-a0b5-d5bb3bd0d4d2";
-
-impl From<Value> for YamlWrap {
-    fn from(yaml: Value) -> Self {
-        YamlWrap(match yaml {
-            Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    Yaml::Integer(i)
-                } else {
-                    Yaml::Real(n.to_string())
-                }
-            },
-            Value::String(s) => Yaml::String(s),
-            Value::Bool(s) => Yaml::Boolean(s),
-            Value::Sequence(seq) => {
-                Yaml::Array(
-                    seq.into_iter()
-                        .map(Into::into)
-                        .map(Self::into_yaml)
-                        .collect(),
-                )
-            },
-            Value::Mapping(map) => {
-                Yaml::Hash(
-                    map.into_iter()
-                        .map(|(k, v)| (Self::into_yaml(k.into()), Self::into_yaml(v.into())))
-                        .collect(),
-                )
-            },
-            Value::Tagged(tagged) => {
-                Yaml::Hash(
-                    // XXX(rust-2021): use `.into_iter()` instead.
-                    IntoIterator::into_iter([
-                        (
-                            Yaml::String(TAGGED_YAML_SMUGGLE_TAG_KEY.into()),
-                            Yaml::String(format!("{}", tagged.tag)),
-                        ),
-                        (
-                            Yaml::String(TAGGED_YAML_SMUGGLE_VALUE_KEY.into()),
-                            YamlWrap::from(tagged.value).into(),
-                        ),
-                    ])
-                    .collect(),
-                )
-            },
-            Value::Null => Yaml::Null,
-        })
-    }
-}
+        impl crate::MergeableNode for $krate::Value {
+            type Key = $krate::Value;
+            type Mapping = $krate::Mapping;
+            type Tag = $krate::value::Tag;
+            type Meta = ();
 
-fn as_smuggled_tagged_value(mut hash: Hash) -> Result<(Tag, Value), Hash> {
-    let value_key = Yaml::String(TAGGED_YAML_SMUGGLE_VALUE_KEY.into());
-    let tag_key = Yaml::String(TAGGED_YAML_SMUGGLE_TAG_KEY.into());
-
-    if hash.len() == 2
-        && hash.contains_key(&value_key)
-        && matches!(hash.get(&tag_key), Some(Yaml::String(_)))
-    {
-        let tag = match hash
-            .remove(&tag_key)
-            .expect("tag was checked in the condition")
-        {
-            Yaml::String(tag) => tag,
-            _ => unreachable!("tag value type was checked in the condition"),
-        };
-        let value = hash
-            .remove(&value_key)
-            .expect("value was checked in the condition");
-
-        Ok((Tag::new(tag), YamlWrap(value).into()))
-    } else {
-        Err(hash)
-    }
-}
+            fn into_parts(self) -> Result<(crate::NodeKind<Self>, Self::Meta), Self> {
+                use crate::NodeKind;
+                use $krate::Value;
 
-impl From<YamlWrap> for Value {
-    fn from(yaml: YamlWrap) -> Self {
-        match yaml.0 {
-            Yaml::Real(f) => {
-                match serde_yaml::from_str(&f) {
-                    Ok(f) => Value::Number(f),
-                    Err(_) => Value::String(f),
-                }
-            },
-            Yaml::Integer(i) => Value::Number(i.into()),
-            Yaml::String(s) => Value::String(s),
-            Yaml::Boolean(b) => Value::Bool(b),
-            Yaml::Array(array) => {
-                Value::Sequence(
-                    array
-                        .into_iter()
-                        .map(|item| {
-                            let wrap: YamlWrap = item.into();
-                            wrap.into()
-                        })
-                        .collect(),
-                )
-            },
-            Yaml::Hash(hash) => {
-                match as_smuggled_tagged_value(hash) {
-                    Ok((tag, value)) => {
-                        Value::Tagged(Box::new(TaggedValue {
+                let kind = match self {
+                    Value::Mapping(mapping) => NodeKind::Mapping(mapping),
+                    Value::Sequence(seq) => NodeKind::Sequence(seq),
+                    Value::Tagged(tagged) => {
+                        let $krate::value::TaggedValue {
                             tag,
                             value,
-                        }))
+                        } = *tagged;
+                        NodeKind::Tagged(tag, Box::new(value))
                     },
-                    Err(hash) => {
-                        Value::Mapping(
-                            hash.into_iter()
-                                .map(|(k, v)| {
-                                    let key: YamlWrap = k.into();
-                                    let value: YamlWrap = v.into();
-                                    (key.into(), value.into())
-                                })
-                                .collect(),
-                        )
+                    _ => return Err(self),
+                };
+                Ok((kind, ()))
+            }
+
+            fn from_parts(kind: crate::NodeKind<Self>, _: Self::Meta) -> Self {
+                use crate::NodeKind;
+                use $krate::Value;
+
+                match kind {
+                    NodeKind::Mapping(mapping) => Value::Mapping(mapping),
+                    NodeKind::Sequence(seq) => Value::Sequence(seq),
+                    NodeKind::Tagged(tag, value) => {
+                        Value::Tagged(Box::new($krate::value::TaggedValue {
+                            tag,
+                            value: *value,
+                        }))
                     },
+                    // Aliases are expanded while loading; these are never produced by
+                    // `into_parts`.
+                    NodeKind::Alias(_) | NodeKind::Undefined => Value::Null,
                 }
-            },
-            Yaml::Alias(_) => unreachable!("alias unsupported"),
-            Yaml::Null => Value::Null,
-            Yaml::BadValue => unreachable!("bad value"),
+            }
+
+            fn is_merge_key(key: &Self::Key) -> bool {
+                matches!(key, $krate::Value::String(s) if s == crate::node::MERGE_KEY)
+            }
+
+            fn merge_in_key<E, F>(key: Self::Key, merge: F) -> Result<Self::Key, E>
+            where
+                F: FnOnce(Self) -> Result<Self, E>,
+            {
+                merge(key)
+            }
         }
-    }
-}
 
-/// Handle merge keys in a serde YAML document.
-pub fn merge_keys_serde(doc: Value) -> Result<Value, MergeKeyError> {
-    merge_keys(YamlWrap::into_yaml(doc.into()))
-        .map(YamlWrap)
-        .map(Into::into)
+        #[doc = concat!("Handle merge keys in a `", stringify!($krate), "` YAML document.")]
+        ///
+        /// Tagged values are kept as they are and numbers are passed through unchanged.
+        pub fn $merge_keys(doc: $krate::Value) -> Result<$krate::Value, crate::MergeKeyError> {
+            crate::merge_keys(doc)
+        }
+
+        #[doc = concat!("Handle merge keys in a `", stringify!($krate), "` YAML document with options.")]
+        ///
+        /// The [`TagPolicy`] of the options controls how tagged merge sources such as
+        /// `<<: !Custom {a: 1}` are handled.
+        ///
+        /// [`TagPolicy`]: crate::TagPolicy
+        pub fn $merge_keys_with_options(
+            doc: $krate::Value,
+            options: &crate::MergeOptions,
+        ) -> Result<$krate::Value, crate::MergeKeyError> {
+            crate::merge_keys_with_options(doc, &crate::AnchorTable::new(), options)
+        }
+    };
 }
+
+#[cfg(feature = "serde_yaml")]
+serde_yaml_value!(serde_yaml, merge_keys_serde, merge_keys_serde_with_options);
+
+#[cfg(feature = "serde_yml")]
+serde_yaml_value!(
+    serde_yml,
+    merge_keys_serde_yml,
+    merge_keys_serde_yml_with_options,
+);
+
+#[cfg(feature = "serde_norway")]
+serde_yaml_value!(
+    serde_norway,
+    merge_keys_serde_norway,
+    merge_keys_serde_norway_with_options,
+);
@@ -6,7 +6,10 @@
 
 use yaml_rust::Yaml;
 
-use crate::merge_keys::merge_keys;
+use crate::anchors::AnchorTable;
+use crate::loader::{load_marked, load_with_anchors};
+use crate::marked::{Marked, MarkedNode};
+use crate::merge_keys::{merge_keys, merge_keys_with_anchors, MergeKeyError};
 
 fn assert_yaml_idempotent(doc: Yaml) {
     assert_eq!(merge_keys(doc.clone()).unwrap(), doc);
@@ -295,16 +298,12 @@ fn test_yaml_spec_examples() {
 }
 
 macro_rules! assert_is_error {
-    ( $doc:expr, $kind:path ) => {
-        let _ = merge_keys($doc).unwrap_err();
+    ( $doc:expr, $kind:pat ) => {
+        let err = merge_keys($doc).unwrap_err();
 
-        /* XXX: irrerfutable
-        if let $kind = err {
-            // Expected error.
-        } else {
+        if !matches!(err, $kind) {
             panic!("unexpected error: {:?}", err);
         }
-        */
     };
 }
 
@@ -337,3 +336,239 @@ fn test_invalid_merge_key_array_values() {
     assert_is_error!(merge_integer, MergeKeyError::InvalidMergeValue);
     assert_is_error!(merge_real, MergeKeyError::InvalidMergeValue);
 }
+
+#[test]
+fn test_resolve_alias() {
+    let anchors = [(1, Yaml::String("anchored".into()))]
+        .iter()
+        .cloned()
+        .collect::<AnchorTable>();
+    let arr = Yaml::Array(vec![Yaml::Alias(1), Yaml::Integer(10)]);
+    let expected = Yaml::Array(vec![Yaml::String("anchored".into()), Yaml::Integer(10)]);
+
+    assert_eq!(merge_keys_with_anchors(arr, &anchors).unwrap(), expected);
+}
+
+#[test]
+fn test_resolve_alias_merge_value() {
+    let anchors = [
+        (1, yaml_hash![(Yaml::Integer(15), Yaml::Integer(10))]),
+        (2, yaml_hash![(Yaml::Integer(20), Yaml::Integer(10))]),
+    ]
+    .iter()
+    .cloned()
+    .collect::<AnchorTable>();
+    let hash_single = yaml_hash![
+        (merge_key(), Yaml::Alias(1)),
+        (Yaml::Integer(10), Yaml::Null),
+    ];
+    let hash_array = yaml_hash![
+        (
+            merge_key(),
+            Yaml::Array(vec![Yaml::Alias(1), Yaml::Alias(2)]),
+        ),
+        (Yaml::Integer(10), Yaml::Null),
+    ];
+    let expected_single = yaml_hash![
+        (Yaml::Integer(10), Yaml::Null),
+        (Yaml::Integer(15), Yaml::Integer(10)),
+    ];
+    let expected_array = yaml_hash![
+        (Yaml::Integer(10), Yaml::Null),
+        (Yaml::Integer(15), Yaml::Integer(10)),
+        (Yaml::Integer(20), Yaml::Integer(10)),
+    ];
+
+    assert_eq!(
+        merge_keys_with_anchors(hash_single, &anchors).unwrap(),
+        expected_single,
+    );
+    assert_eq!(
+        merge_keys_with_anchors(hash_array, &anchors).unwrap(),
+        expected_array,
+    );
+}
+
+#[test]
+fn test_resolve_alias_nested_merge_keys() {
+    let anchors = [
+        (1, yaml_hash![(Yaml::Integer(15), Yaml::Integer(10))]),
+        (
+            2,
+            yaml_hash![
+                (merge_key(), Yaml::Alias(1)),
+                (Yaml::Integer(20), Yaml::Integer(10)),
+            ],
+        ),
+    ]
+    .iter()
+    .cloned()
+    .collect::<AnchorTable>();
+    let hash = yaml_hash![
+        (merge_key(), Yaml::Alias(2)),
+        (Yaml::Integer(10), Yaml::Null)
+    ];
+    let expected = yaml_hash![
+        (Yaml::Integer(10), Yaml::Null),
+        (Yaml::Integer(20), Yaml::Integer(10)),
+        (Yaml::Integer(15), Yaml::Integer(10)),
+    ];
+
+    assert_eq!(merge_keys_with_anchors(hash, &anchors).unwrap(), expected);
+}
+
+#[test]
+fn test_unresolved_alias() {
+    let hash = yaml_hash![(merge_key(), Yaml::Alias(1))];
+    let merge_arr = yaml_hash![(merge_key(), Yaml::Array(vec![Yaml::Alias(1)]))];
+
+    assert_is_error!(hash, MergeKeyError::UnresolvedAlias(1));
+    assert_is_error!(merge_arr, MergeKeyError::UnresolvedAlias(1));
+}
+
+#[test]
+fn test_unresolved_alias_kept() {
+    // Aliases which are not merged are left alone.
+    let arr = Yaml::Array(vec![Yaml::Alias(1)]);
+    let hash = yaml_hash![(Yaml::Alias(1), Yaml::Alias(2))];
+
+    assert_eq!(merge_keys(arr.clone()).unwrap(), arr);
+    assert_eq!(merge_keys(hash.clone()).unwrap(), hash);
+}
+
+#[test]
+fn test_recursive_alias() {
+    let anchors = [(1, yaml_hash![(merge_key(), Yaml::Alias(1))])]
+        .iter()
+        .cloned()
+        .collect::<AnchorTable>();
+    let hash = yaml_hash![(Yaml::Null, Yaml::Alias(1))];
+
+    let err = merge_keys_with_anchors(hash, &anchors).unwrap_err();
+    if !matches!(err, MergeKeyError::RecursiveAlias(1)) {
+        panic!("unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn test_undefined_alias_merge_value() {
+    let merge_bad = yaml_hash![(merge_key(), Yaml::BadValue)];
+    let merge_array_bad = yaml_hash![(merge_key(), Yaml::Array(vec![Yaml::BadValue]))];
+
+    assert_is_error!(
+        merge_bad,
+        MergeKeyError::UndefinedAlias {
+            anchor: None,
+            marker: None,
+        }
+    );
+    assert_is_error!(
+        merge_array_bad,
+        MergeKeyError::UndefinedAlias {
+            anchor: None,
+            marker: None,
+        }
+    );
+}
+
+#[test]
+fn test_load_with_anchors() {
+    let raw = "\
+ref: &ref
+    merged_key: merged
+dict:
+    <<: *ref
+    top_key: given
+";
+    let (mut docs, anchors) = load_with_anchors(raw).unwrap();
+    let doc = docs.remove(0);
+
+    assert_eq!(doc["dict"]["<<"], Yaml::Alias(1));
+    assert_eq!(anchors.len(), 1);
+
+    let merged = merge_keys_with_anchors(doc, &anchors).unwrap();
+
+    assert_eq!(merged["dict"]["merged_key"], Yaml::String("merged".into()));
+    assert_eq!(merged["dict"]["top_key"], Yaml::String("given".into()));
+}
+
+#[test]
+fn test_load_with_anchors_undefined_alias() {
+    let raw = "\
+ref: &ref
+    <<: *ref
+    key: value
+";
+    let (mut docs, anchors) = load_with_anchors(raw).unwrap();
+    let err = merge_keys_with_anchors(docs.remove(0), &anchors).unwrap_err();
+
+    if let MergeKeyError::UndefinedAlias {
+        anchor: Some(anchor),
+        marker: Some(marker),
+    } = &err
+    {
+        assert_eq!(anchor, "ref");
+        assert_eq!(marker.line(), 2);
+        assert_eq!(marker.col(), 8);
+    } else {
+        panic!("unexpected error: {:?}", err);
+    }
+    assert_eq!(err.to_string(), "undefined alias `*ref` at line 2 column 9");
+}
+
+#[test]
+fn test_load_with_anchors_undefined_alias_unicode() {
+    // Markers count characters rather than bytes.
+    let raw = "\
+clé: été
+ref: &ref
+    <<: *ref
+";
+    let (mut docs, anchors) = load_with_anchors(raw).unwrap();
+    let err = merge_keys_with_anchors(docs.remove(0), &anchors).unwrap_err();
+
+    assert_eq!(err.to_string(), "undefined alias `*ref` at line 3 column 9");
+}
+
+#[test]
+fn test_load_marked() {
+    let raw = "\
+ref: &ref
+    key: value
+dict:
+    <<: *ref
+    1: one
+    1: again
+";
+    let (mut docs, anchors) = load_marked(raw).unwrap();
+    let doc = docs.remove(0);
+
+    let position = |node: &Marked| (node.marker.line(), node.marker.col());
+    let entries = match doc.node {
+        MarkedNode::Mapping(entries) => entries,
+        node => panic!("unexpected node: {:?}", node),
+    };
+    assert_eq!(entries.len(), 2);
+    let (key, dict) = &entries[1];
+    assert!(key.is_key("dict"));
+    assert_eq!(position(key), (3, 0));
+
+    let entries = match &dict.node {
+        MarkedNode::Mapping(entries) => entries,
+        node => panic!("unexpected node: {:?}", node),
+    };
+    // Duplicate keys are kept.
+    assert_eq!(entries.len(), 3);
+    let (merge_key, alias) = &entries[0];
+    assert!(merge_key.is_key("<<"));
+    assert_eq!(alias.node, MarkedNode::Alias(1, "ref".into()));
+    assert_eq!(position(alias), (4, 8));
+    assert!(entries[1].0.is_key("1"));
+    assert_eq!(entries[1].0.node, MarkedNode::Scalar(Yaml::Integer(1)));
+    assert_eq!(position(&entries[2].0), (6, 4));
+
+    match &anchors.get(1).unwrap().node {
+        MarkedNode::Mapping(entries) => assert_eq!(position(&entries[0].0), (2, 4)),
+        node => panic!("unexpected node: {:?}", node),
+    }
+}
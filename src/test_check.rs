@@ -0,0 +1,135 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use yaml_rust::YamlLoader;
+
+use crate::{check, load_merged, ErrorKind};
+
+#[test]
+fn test_load_merged() {
+    let raw = "\
+ref: &ref
+    a: 1
+dict:
+    <<: *ref
+    b: 2
+---
+- <<: {c: 3}
+";
+    let merged = "\
+ref:
+    a: 1
+dict:
+    b: 2
+    a: 1
+---
+- c: 3
+";
+
+    assert_eq!(
+        load_merged(raw).unwrap(),
+        YamlLoader::load_from_str(merged).unwrap(),
+    );
+    check(raw).unwrap();
+}
+
+#[test]
+fn test_check_syntax_error() {
+    let err = check("a:\n  b: c: d\n").unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Syntax);
+    assert_eq!(err.location().unwrap().line(), 2);
+}
+
+#[test]
+fn test_check_invalid_merge_value() {
+    let raw = "\
+a:
+    b: 1
+c:
+    d:
+        <<: 1
+";
+    let err = check(raw).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidMergeValue);
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 5);
+    assert_eq!(location.column(), 9);
+}
+
+#[test]
+fn test_check_first_failing_merge_key() {
+    let raw = "\
+- <<: {a: 1}
+- <<: [{a: 1}, 2]
+- <<: 3
+";
+    let err = check(raw).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidMergeValue);
+    assert_eq!(err.location().unwrap().line(), 2);
+}
+
+#[test]
+fn test_check_nested_merge_key() {
+    let raw = "\
+a:
+    <<:
+        <<: 1
+";
+    let err = check(raw).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::InvalidMergeValue);
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 3);
+    assert_eq!(location.column(), 9);
+}
+
+#[test]
+fn test_check_later_document() {
+    let raw = "\
+a: 1
+---
+b:
+    <<: [1]
+";
+    let err = check(raw).unwrap_err();
+
+    assert_eq!(err.location().unwrap().line(), 4);
+}
+
+#[test]
+fn test_check_undefined_alias() {
+    let raw = "\
+ref: &ref
+    <<: *ref
+";
+    let err = check(raw).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::UndefinedAlias);
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 2);
+    assert_eq!(location.column(), 9);
+}
+
+#[test]
+fn test_check_unknown_anchor() {
+    // The parser rejects aliases to anchors which have not appeared at all.
+    let raw = "\
+ref: &ref
+    a: 1
+dict:
+    <<: *typo
+";
+    let err = check(raw).unwrap_err();
+
+    assert_eq!(err.kind(), ErrorKind::Syntax);
+    assert!(err.to_string().contains("unknown anchor"), "{}", err);
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 4);
+    assert_eq!(location.column(), 9);
+}
@@ -0,0 +1,143 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::{
+    from_reader, from_reader_documents, from_slice, from_str, from_str_documents, Error,
+    MergeKeyError,
+};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Service {
+    name: String,
+    port: u16,
+    replicas: u64,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config {
+    services: BTreeMap<String, Service>,
+}
+
+const CONFIG: &str = "\
+defaults: &defaults
+  port: 80
+  replicas: 18446744073709551615
+services:
+  web:
+    <<: *defaults
+    name: web
+  api:
+    <<: *defaults
+    name: api
+    port: 8080
+";
+
+fn expected_config() -> Config {
+    let service = |name: &str, port| {
+        Service {
+            name: name.into(),
+            port,
+            replicas: u64::MAX,
+        }
+    };
+
+    Config {
+        services: vec![
+            ("web".into(), service("web", 80)),
+            ("api".into(), service("api", 8080)),
+        ]
+        .into_iter()
+        .collect(),
+    }
+}
+
+#[test]
+fn test_from_str() {
+    assert_eq!(from_str::<Config>(CONFIG).unwrap(), expected_config());
+}
+
+#[test]
+fn test_from_slice() {
+    assert_eq!(
+        from_slice::<Config>(CONFIG.as_bytes()).unwrap(),
+        expected_config(),
+    );
+}
+
+#[test]
+fn test_from_reader() {
+    assert_eq!(
+        from_reader::<_, Config>(CONFIG.as_bytes()).unwrap(),
+        expected_config(),
+    );
+}
+
+#[test]
+fn test_from_str_documents() {
+    let raw = "\
+base: &base {port: 80, replicas: 1}
+service: {<<: *base, name: first}
+---
+base: &base {port: 81, replicas: 2}
+service: {<<: *base, name: second}
+";
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Doc {
+        service: Service,
+    }
+
+    let docs = from_str_documents::<Doc>(raw)
+        .map(|doc| doc.unwrap().service)
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        docs,
+        [
+            Service {
+                name: "first".into(),
+                port: 80,
+                replicas: 1,
+            },
+            Service {
+                name: "second".into(),
+                port: 81,
+                replicas: 2,
+            },
+        ],
+    );
+}
+
+#[test]
+fn test_from_reader_documents_errors() {
+    let raw = "{<<: 1}\n---\n{a: 1}\n---\n{a: [}\n";
+    let mut docs = from_reader_documents::<_, BTreeMap<String, u8>>(raw.as_bytes());
+
+    assert!(matches!(
+        docs.next(),
+        Some(Err(Error::MergeKey(MergeKeyError::InvalidMergeValue)))
+    ));
+    assert_eq!(docs.next().unwrap().unwrap()["a"], 1);
+    assert!(matches!(docs.next(), Some(Err(Error::Serde(_)))));
+}
+
+#[test]
+fn test_errors() {
+    let parse = from_str::<Config>("services: [").unwrap_err();
+    let merge = from_str::<Config>("services: {<<: 1}").unwrap_err();
+    let deserialize = from_str::<Config>("services: 1").unwrap_err();
+
+    assert!(matches!(parse, Error::Serde(_)));
+    assert!(matches!(
+        merge,
+        Error::MergeKey(MergeKeyError::InvalidMergeValue)
+    ));
+    assert!(matches!(deserialize, Error::Serde(_)));
+}
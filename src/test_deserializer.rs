@@ -0,0 +1,147 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::{merge_keys_serde, MergeKeysDeserializer};
+
+fn deserialize<'de, T>(raw: &'de str) -> Result<T, serde_yaml::Error>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(MergeKeysDeserializer::new(
+        serde_yaml::Deserializer::from_str(raw),
+    ))
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Limits {
+    cpu: u32,
+    memory: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+enum Action {
+    Run { command: String, limits: Limits },
+    Skip(Limits),
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Job {
+    name: String,
+    limits: Limits,
+    action: Action,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Config {
+    jobs: Vec<Job>,
+    #[serde(default)]
+    templates: BTreeMap<String, Value>,
+}
+
+#[test]
+fn test_typed() {
+    let raw = "\
+templates:
+  limits: &limits
+    cpu: 1
+    memory: 1G
+  job: &job
+    limits: *limits
+jobs:
+  - <<: *job
+    name: build
+    action: !Run
+      <<: {command: make}
+      limits:
+        <<: *limits
+        cpu: 4
+  - name: skip
+    <<: *job
+    action: !Skip
+      <<: *limits
+      memory: ~
+";
+    let config: Config = deserialize(raw).unwrap();
+
+    assert_eq!(
+        config.jobs,
+        [
+            Job {
+                name: "build".into(),
+                limits: Limits {
+                    cpu: 1,
+                    memory: Some("1G".into()),
+                },
+                action: Action::Run {
+                    command: "make".into(),
+                    limits: Limits {
+                        cpu: 4,
+                        memory: Some("1G".into()),
+                    },
+                },
+            },
+            Job {
+                name: "skip".into(),
+                limits: Limits {
+                    cpu: 1,
+                    memory: Some("1G".into()),
+                },
+                action: Action::Skip(Limits {
+                    cpu: 1,
+                    memory: None,
+                }),
+            },
+        ],
+    );
+}
+
+#[test]
+fn test_matches_value_merging() {
+    let raw = "\
+- &CENTER { x: 1, y: 2 }
+- &LEFT { x: 0, y: 2 }
+- &BIG { r: 10 }
+- &SMALL { r: 1 }
+- x: 1
+  y: 2
+  r: 10
+  label: center/big
+- << : *CENTER
+  r: 10
+  label: center/big
+- << : [ *CENTER, *BIG ]
+  label: center/big
+- << : [ *BIG, *LEFT, *SMALL ]
+  x: 1
+  label: center/big
+- ? {<<: {a: 1}, b: 2}
+  : {<<: [{c: 3}, !tag {d: 4}]}
+";
+    let streamed: Value = deserialize(raw).unwrap();
+    let merged = merge_keys_serde(serde_yaml::from_str(raw).unwrap()).unwrap();
+
+    assert_eq!(streamed, merged);
+}
+
+#[test]
+fn test_invalid_merge_value() {
+    let err = deserialize::<Value>("{a: {<<: 1}}").unwrap_err();
+
+    assert!(
+        err.to_string()
+            .contains("only mappings and arrays of mappings may be merged"),
+        "unexpected error: {}",
+        err,
+    );
+}
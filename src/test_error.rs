@@ -0,0 +1,114 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use yaml_rust::YamlLoader;
+
+use crate::{load_with_anchors, merge_keys_with_anchors, Error, ErrorKind, MergeKeyError};
+
+#[test]
+fn test_scan_error() {
+    let err: Error = YamlLoader::load_from_str("a:\n  b: c: d\n")
+        .unwrap_err()
+        .into();
+
+    assert_eq!(err.kind(), ErrorKind::Syntax);
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 2);
+    assert_eq!(location.column(), 7);
+}
+
+#[test]
+fn test_location_index() {
+    let raw = "é: x
+b: c: d
+";
+    let err: Error = YamlLoader::load_from_str(raw).unwrap_err().into();
+    let location = err.location().unwrap();
+
+    assert_eq!(location.line(), 2);
+    assert_eq!(location.column(), 5);
+    assert_eq!(location.char_index(), Some(9));
+    assert_eq!(location.byte_index(), None);
+}
+
+#[test]
+fn test_merge_key_error() {
+    let raw = "\
+ref: &ref
+    <<: *ref
+    key: value
+";
+    let (mut docs, anchors) = load_with_anchors(raw).unwrap();
+    let err: Error = merge_keys_with_anchors(docs.remove(0), &anchors)
+        .unwrap_err()
+        .into();
+
+    assert_eq!(err.kind(), ErrorKind::UndefinedAlias);
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 2);
+    assert_eq!(location.column(), 9);
+    assert_eq!(err.to_string(), "undefined alias `*ref` at line 2 column 9");
+}
+
+#[test]
+fn test_merge_key_error_kinds() {
+    let errors = [
+        (
+            MergeKeyError::InvalidMergeValue,
+            ErrorKind::InvalidMergeValue,
+        ),
+        (
+            MergeKeyError::UnresolvedAlias(0),
+            ErrorKind::UnresolvedAlias,
+        ),
+        (MergeKeyError::undefined_alias(), ErrorKind::UndefinedAlias),
+        (MergeKeyError::RecursiveAlias(0), ErrorKind::RecursiveAlias),
+        (
+            MergeKeyError::TaggedMergeValue("!tag".into()),
+            ErrorKind::TaggedMergeValue,
+        ),
+        (
+            MergeKeyError::TagMismatch {
+                local: None,
+                merged: "!tag".into(),
+            },
+            ErrorKind::TagMismatch,
+        ),
+    ];
+
+    for (err, kind) in errors {
+        assert_eq!(err.kind(), kind);
+        assert_eq!(err.location(), None);
+        assert_eq!(Error::from(err).kind(), kind);
+    }
+}
+
+#[cfg(feature = "serde_yaml")]
+#[test]
+fn test_serde_errors() {
+    use std::collections::BTreeMap;
+
+    let syntax = crate::from_str::<BTreeMap<String, u8>>("a:\n  b: c: d\n").unwrap_err();
+    let deserialize = crate::from_str::<BTreeMap<String, u8>>("a: b").unwrap_err();
+    let merge = crate::from_str::<BTreeMap<String, u8>>("<<: 1").unwrap_err();
+
+    assert_eq!(syntax.kind(), ErrorKind::Syntax);
+    assert_eq!(syntax.location().unwrap().line(), 2);
+
+    let location = crate::from_str::<BTreeMap<String, u8>>("é: x\nb: c: d\n")
+        .unwrap_err()
+        .location()
+        .unwrap();
+    assert_eq!(location.line(), 2);
+    assert_eq!(location.column(), 5);
+    // `é` is two bytes.
+    assert_eq!(location.byte_index(), Some(10));
+    assert_eq!(location.char_index(), None);
+    assert_eq!(deserialize.kind(), ErrorKind::Deserialize);
+    assert_eq!(deserialize.location(), None);
+    assert_eq!(merge.kind(), ErrorKind::InvalidMergeValue);
+    assert_eq!(merge.location(), None);
+}
@@ -0,0 +1,260 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::fs;
+use std::path::Path;
+
+use yaml_rust::YamlLoader;
+
+use crate::test_dir::TestDir;
+use crate::{load_merged, Error, ErrorKind, IncludeError, Includes};
+
+fn assert_include_error<'a>(err: &'a Error, path: &[&str]) -> &'a IncludeError {
+    assert_eq!(err.kind(), ErrorKind::Include);
+    if let Error::Include {
+        error,
+        chain,
+    } = err
+    {
+        let files = chain
+            .iter()
+            .map(|site| site.file().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(files, path);
+        error
+    } else {
+        panic!("unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn test_include_merge_value() {
+    let dir = TestDir::new("merge-value");
+    dir.write("base.yml", "a: 1\nb: 2\n");
+    let main = dir.write("main.yml", "<<: !include base.yml\nb: 3\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let docs = includes.load_merged(main).unwrap();
+
+    assert_eq!(docs, YamlLoader::load_from_str("b: 3\na: 1\n").unwrap());
+}
+
+#[test]
+fn test_include_merge_sequence() {
+    let dir = TestDir::new("merge-sequence");
+    dir.write("base.yml", "a: 1\nb: 1\n");
+    let main = dir.write(
+        "main.yml",
+        "local: &local\n  b: 2\ndict:\n  <<: [*local, !include base.yml]\n",
+    );
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let docs = includes.load_merged(main).unwrap();
+
+    assert_eq!(
+        docs,
+        YamlLoader::load_from_str("local:\n  b: 2\ndict:\n  b: 2\n  a: 1\n").unwrap(),
+    );
+}
+
+#[test]
+fn test_include_relative_to_including_file() {
+    let dir = TestDir::new("relative");
+    dir.write("common/base.yml", "<<: !include defaults.yml\nb: 2\n");
+    dir.write("common/defaults.yml", "a: 1\n");
+    let main = dir.write("app/main.yml", "<<: !include ../common/base.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let docs = includes.load_merged(main).unwrap();
+
+    assert_eq!(docs, YamlLoader::load_from_str("b: 2\na: 1\n").unwrap());
+}
+
+#[test]
+fn test_include_merges_included_file() {
+    let dir = TestDir::new("merges-included");
+    dir.write("base.yml", "ref: &ref\n  a: 1\n<<: *ref\n");
+    let main = dir.write("main.yml", "<<: !include base.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let docs = includes.load_merged(main).unwrap();
+
+    assert_eq!(
+        docs,
+        YamlLoader::load_from_str("ref:\n  a: 1\na: 1\n").unwrap(),
+    );
+}
+
+#[test]
+fn test_include_cached() {
+    let dir = TestDir::new("cached");
+    let base = dir.write("base.yml", "a: 1\n");
+    let main = dir.write(
+        "main.yml",
+        "x:\n  <<: !include base.yml\ny:\n  <<: !include base.yml\n",
+    );
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let docs = includes.load_merged(&main).unwrap();
+    assert_eq!(
+        docs,
+        YamlLoader::load_from_str("x:\n  a: 1\ny:\n  a: 1\n").unwrap(),
+    );
+
+    // Later loads use the cached contents of the included file.
+    fs::write(base, "a: 2\n").unwrap();
+    let docs = includes.load_merged(&main).unwrap();
+    assert_eq!(
+        docs,
+        YamlLoader::load_from_str("x:\n  a: 1\ny:\n  a: 1\n").unwrap(),
+    );
+}
+
+#[test]
+fn test_include_outside_root() {
+    let dir = TestDir::new("outside-root");
+    dir.write("base.yml", "a: 1\n");
+    let main = dir.write("sub/main.yml", "<<: !include ../base.yml\n");
+
+    let mut includes = Includes::new(dir.path().join("sub")).unwrap();
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml"]);
+    if let IncludeError::OutsideRoot {
+        ..
+    } = error
+    {
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+
+    // Missing files outside of the root are not distinguished from existing ones.
+    let main = dir.write("sub/main.yml", "<<: !include ../missing.yml\n");
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml"]);
+    if let IncludeError::OutsideRoot {
+        ..
+    } = error
+    {
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+}
+
+#[test]
+fn test_include_missing_file() {
+    let dir = TestDir::new("missing");
+    let main = dir.write("main.yml", "<<: !include missing.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml"]);
+    if let IncludeError::Read {
+        path, ..
+    } = error
+    {
+        assert_eq!(path, Path::new("missing.yml"));
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+}
+
+#[test]
+fn test_include_cycle() {
+    let dir = TestDir::new("cycle");
+    dir.write("a.yml", "<<: !include b.yml\n");
+    dir.write("b.yml", "x:\n  <<: !include a.yml\n");
+    let main = dir.write("main.yml", "<<: !include a.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml", "a.yml", "b.yml"]);
+    if let IncludeError::Cycle {
+        path,
+    } = error
+    {
+        assert_eq!(path, Path::new("a.yml"));
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+    assert_eq!(
+        err.to_string(),
+        "`a.yml` includes itself (included from main.yml:1:14 -> a.yml:1:14 -> b.yml:2:16)",
+    );
+    let location = err.location().unwrap();
+    assert_eq!(location.line(), 1);
+    assert_eq!(location.column(), 14);
+}
+
+#[test]
+fn test_include_invalid_file() {
+    let dir = TestDir::new("invalid");
+    dir.write("base.yml", "a:\n  <<: 1\n");
+    let main = dir.write("main.yml", "x: 1\n<<: !include base.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml"]);
+    if let IncludeError::Invalid {
+        path,
+        error,
+    } = error
+    {
+        assert_eq!(path, Path::new("base.yml"));
+        assert_eq!(error.kind(), ErrorKind::InvalidMergeValue);
+        assert_eq!(error.location().unwrap().line(), 2);
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+    assert_eq!(err.location().unwrap().line(), 2);
+}
+
+#[test]
+fn test_include_document_count() {
+    let dir = TestDir::new("document-count");
+    dir.write("base.yml", "a: 1\n---\nb: 2\n");
+    let main = dir.write("main.yml", "<<: !include base.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml"]);
+    if let IncludeError::DocumentCount {
+        count, ..
+    } = error
+    {
+        assert_eq!(*count, 2);
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+}
+
+#[test]
+fn test_include_not_merge_source() {
+    let dir = TestDir::new("not-merge-source");
+    dir.write("base.yml", "a: 1\n");
+    let main = dir.write("main.yml", "x: !include base.yml\n");
+
+    let mut includes = Includes::new(dir.path()).unwrap();
+    let err = includes.load_merged(main).unwrap_err();
+
+    let error = assert_include_error(&err, &["main.yml"]);
+    if let IncludeError::NotMergeSource = error {
+    } else {
+        panic!("unexpected error: {:?}", error);
+    }
+}
+
+#[test]
+fn test_include_unsupported_without_includes() {
+    let docs = load_merged("x: !include base.yml\n").unwrap();
+
+    assert_eq!(docs, YamlLoader::load_from_str("x: base.yml\n").unwrap());
+}
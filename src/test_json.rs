@@ -0,0 +1,285 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use serde_json::{json, Value};
+
+use crate::json::merge_keys_json;
+use crate::MergeKeyError;
+
+fn assert_json_idempotent(doc: Value) {
+    assert_eq!(merge_keys_json(doc.clone()).unwrap(), doc);
+}
+
+fn object_keys(doc: &Value) -> Vec<&str> {
+    doc.as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn test_ignore_non_containers() {
+    let null = Value::Null;
+    let bool_true = Value::Bool(true);
+    let bool_false = Value::Bool(false);
+    let string = Value::String("".into());
+    let integer = json!(1234);
+    let real = json!(0.02);
+
+    assert_json_idempotent(null);
+    assert_json_idempotent(bool_true);
+    assert_json_idempotent(bool_false);
+    assert_json_idempotent(string);
+    assert_json_idempotent(integer);
+    assert_json_idempotent(real);
+}
+
+#[test]
+fn test_ignore_container_no_merge_keys() {
+    let arr = json!([10, 100]);
+    let object = json!({
+        "10": null,
+        "100": "string",
+    });
+
+    assert_json_idempotent(arr);
+    assert_json_idempotent(object);
+}
+
+#[test]
+fn test_remove_merge_keys() {
+    let object = json!({
+        "<<": {},
+        "10": null,
+        "100": "string",
+    });
+    let expected = json!({
+        "10": null,
+        "100": "string",
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_handle_merge_keys() {
+    let object = json!({
+        "<<": { "15": null },
+        "10": null,
+        "100": "string",
+    });
+    let expected = json!({
+        "10": null,
+        "100": "string",
+        "15": null,
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_key_precedence() {
+    let object = json!({
+        "<<": { "10": 10 },
+        "10": null,
+        "100": "string",
+    });
+    let expected = json!({
+        "10": null,
+        "100": "string",
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_key_array() {
+    let object = json!({
+        "<<": [
+            { "15": 10 },
+            { "20": 10 },
+        ],
+        "10": null,
+        "100": "string",
+    });
+    let expected = json!({
+        "10": null,
+        "100": "string",
+        "15": 10,
+        "20": 10,
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_key_array_precedence() {
+    let object = json!({
+        "<<": [
+            { "15": 10 },
+            { "15": 20 },
+        ],
+        "10": null,
+        "100": "string",
+    });
+    let expected = json!({
+        "10": null,
+        "100": "string",
+        "15": 10,
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_key_nested_array() {
+    let object = json!([{
+        "<<": [
+            { "15": 10 },
+            { "15": 20 },
+        ],
+        "10": null,
+        "100": "string",
+    }]);
+    let expected = json!([{
+        "10": null,
+        "100": "string",
+        "15": 10,
+    }]);
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_key_nested_object_value() {
+    let object = json!({
+        "null": {
+            "<<": [
+                { "15": 10 },
+                { "15": 20 },
+            ],
+            "10": null,
+            "100": "string",
+        },
+    });
+    let expected = json!({
+        "null": {
+            "10": null,
+            "100": "string",
+            "15": 10,
+        },
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_key_nested_merge_value() {
+    let object = json!({
+        "<<": {
+            "<<": { "20": 10 },
+            "15": 10,
+        },
+        "10": null,
+    });
+    let expected = json!({
+        "10": null,
+        "15": 10,
+        "20": 10,
+    });
+
+    assert_eq!(merge_keys_json(object).unwrap(), expected);
+}
+
+#[test]
+fn test_yaml_spec_examples() {
+    let center = json!({ "x": 1, "y": 2 });
+    let left = json!({ "x": 0, "y": 2 });
+    let big = json!({ "r": 10 });
+    let small = json!({ "r": 1 });
+
+    let explicit = json!({
+        "x": 1,
+        "y": 2,
+        "r": 10,
+        "label": "center/big",
+    });
+    let merge_one_map = json!({
+        "<<": center,
+        "r": 10,
+        "label": "center/big",
+    });
+    let merge_multiple_maps = json!({
+        "<<": [center, big],
+        "label": "center/big",
+    });
+    let overrides = json!({
+        "<<": [big, left, small],
+        "x": 1,
+        "label": "center/big",
+    });
+
+    assert_eq!(merge_keys_json(explicit.clone()).unwrap(), explicit);
+    assert_eq!(merge_keys_json(merge_one_map).unwrap(), explicit);
+    assert_eq!(merge_keys_json(merge_multiple_maps).unwrap(), explicit);
+    assert_eq!(merge_keys_json(overrides).unwrap(), explicit);
+}
+
+#[test]
+fn test_merge_key_order() {
+    let object = json!({
+        "a": 1,
+        "<<": { "d": 4, "a": 0 },
+        "b": 2,
+        "c": 3,
+    });
+    let merged = merge_keys_json(object).unwrap();
+
+    // Local keys keep their order with merged keys afterwards (this is also the sorted order
+    // when `serde_json` does not preserve order).
+    assert_eq!(object_keys(&merged), ["a", "b", "c", "d"]);
+}
+
+macro_rules! assert_is_error {
+    ( $doc:expr, $kind:pat ) => {
+        let err = merge_keys_json($doc).unwrap_err();
+
+        if !matches!(err, $kind) {
+            panic!("unexpected error: {:?}", err);
+        }
+    };
+}
+
+#[test]
+fn test_invalid_merge_key_values() {
+    let merge_null = json!({ "<<": null });
+    let merge_bool = json!({ "<<": false });
+    let merge_string = json!({ "<<": "" });
+    let merge_integer = json!({ "<<": 0 });
+    let merge_real = json!({ "<<": 0.02 });
+
+    assert_is_error!(merge_null, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_bool, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_string, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_integer, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_real, MergeKeyError::InvalidMergeValue);
+}
+
+#[test]
+fn test_invalid_merge_key_array_values() {
+    let merge_null = json!({ "<<": [null] });
+    let merge_bool = json!({ "<<": [false] });
+    let merge_string = json!({ "<<": [""] });
+    let merge_integer = json!({ "<<": [0] });
+    let merge_real = json!({ "<<": [0.02] });
+
+    assert_is_error!(merge_null, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_bool, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_string, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_integer, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_real, MergeKeyError::InvalidMergeValue);
+}
@@ -0,0 +1,193 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde_yaml::Value;
+
+use crate::{MergeOptions, Merged, TagPolicy};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Limits {
+    cpu: u32,
+    memory: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Payload {
+    name: String,
+    config: Merged<BTreeMap<String, Limits>>,
+    #[serde(deserialize_with = "crate::deserialize_merged")]
+    raw: Value,
+}
+
+const PAYLOAD: &str = "\
+name: payload
+config:
+  base: &base
+    cpu: 1
+    memory: 1G
+  big:
+    <<: *base
+    cpu: 8
+raw:
+  - {<<: {a: 1}, b: 2}
+";
+
+#[test]
+fn test_merged() {
+    let payload: Payload = serde_yaml::from_str(PAYLOAD).unwrap();
+
+    assert_eq!(payload.name, "payload");
+    assert_eq!(
+        payload.config["big"],
+        Limits {
+            cpu: 8,
+            memory: "1G".into(),
+        },
+    );
+    assert_eq!(
+        payload.raw,
+        serde_yaml::from_str::<Value>("[{b: 2, a: 1}]").unwrap(),
+    );
+    assert_eq!(payload.config.into_inner().len(), 2);
+}
+
+#[test]
+fn test_merged_error_path() {
+    let raw = "\
+name: payload
+config: {}
+raw:
+  - ok: {}
+  - nested:
+      list: [{}, {<<: [{a: 1}, 2]}]
+";
+    let err = serde_yaml::from_str::<Payload>(raw).unwrap_err();
+
+    assert!(
+        err.to_string().starts_with(
+            "only mappings and arrays of mappings may be merged at `[1].nested.list[1]`",
+        ),
+        "unexpected error: {}",
+        err,
+    );
+}
+
+#[test]
+fn test_merged_error_root() {
+    let err = serde_yaml::from_str::<Merged<Value>>("{<<: 1}").unwrap_err();
+
+    assert!(
+        err.to_string()
+            .starts_with("only mappings and arrays of mappings may be merged at `.`"),
+        "unexpected error: {}",
+        err,
+    );
+}
+
+#[test]
+fn test_merged_error_path_merge_value() {
+    let raw = "\
+name: payload
+config: {}
+raw:
+  dict:
+    <<:
+      - {a: 1}
+      - {<<: 2}
+";
+    let err = serde_yaml::from_str::<Payload>(raw).unwrap_err();
+
+    assert!(
+        err.to_string()
+            .starts_with("only mappings and arrays of mappings may be merged at `dict.<<[1]`"),
+        "unexpected error: {}",
+        err,
+    );
+
+    let raw = "\
+name: payload
+config: {}
+raw:
+  dict:
+    ? {<<: 3}
+    : 1
+";
+    let err = serde_yaml::from_str::<Payload>(raw).unwrap_err();
+
+    assert!(
+        err.to_string()
+            .starts_with("only mappings and arrays of mappings may be merged at `dict.?`"),
+        "unexpected error: {}",
+        err,
+    );
+}
+
+fn deserialize_rejecting_tags<'de, D>(deserializer: D) -> Result<Value, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let options = MergeOptions::new().with_tag_policy(TagPolicy::Reject);
+    crate::deserialize_merged_with_options(deserializer, &options)
+}
+
+#[derive(Debug, Deserialize)]
+struct RejectTags {
+    #[serde(deserialize_with = "deserialize_rejecting_tags")]
+    raw: Value,
+}
+
+#[test]
+fn test_merged_with_options() {
+    let raw = "\
+raw:
+  untagged: {<<: {a: 1}}
+  list: [{<<: !Custom {a: 1}}]
+";
+    let err = serde_yaml::from_str::<RejectTags>(raw).unwrap_err();
+
+    assert!(
+        err.to_string()
+            .starts_with("tagged value `!Custom` may not be merged at `list[0]`"),
+        "unexpected error: {}",
+        err,
+    );
+
+    let payload: RejectTags = serde_yaml::from_str("raw: {<<: {a: 1}, b: 2}").unwrap();
+    assert_eq!(payload.raw["a"], 1);
+}
+
+#[test]
+fn test_merged_tagged() {
+    let merged: Merged<Value> = serde_yaml::from_str("!Local {<<: !Custom {a: 1}}").unwrap();
+
+    assert_eq!(
+        merged.into_inner(),
+        serde_yaml::from_str::<Value>("!Local {a: 1}").unwrap(),
+    );
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn test_merged_json() {
+    let raw = r#"{
+        "name": "payload",
+        "config": {"big": {"<<": {"cpu": 1, "memory": "1G"}, "cpu": 8}},
+        "raw": {"<<": [{"a": 1}]}
+    }"#;
+    let payload: Payload = serde_json::from_str(raw).unwrap();
+
+    assert_eq!(
+        payload.config["big"],
+        Limits {
+            cpu: 8,
+            memory: "1G".into(),
+        },
+    );
+    assert_eq!(payload.raw["a"], 1);
+}
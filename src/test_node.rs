@@ -0,0 +1,159 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::iter::FromIterator;
+
+use crate::{
+    merge_keys, merge_keys_with_anchors, AnchorTable, MergeKeyError, MergeableMapping,
+    MergeableNode, NodeKind,
+};
+
+/// A minimal tree type which is not provided by any YAML crate.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Scalar(&'static str),
+    Map(Map),
+    List(Vec<Node>),
+    Tagged(&'static str, Box<Node>),
+    Ref(usize),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct Map(Vec<(&'static str, Node)>);
+
+impl IntoIterator for Map {
+    type Item = (&'static str, Node);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl FromIterator<(&'static str, Node)> for Map {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (&'static str, Node)>,
+    {
+        Map(iter.into_iter().collect())
+    }
+}
+
+impl MergeableMapping for Map {
+    type Key = &'static str;
+    type Value = Node;
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        self.0.iter().any(|(k, _)| k == key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) {
+        self.0.push((key, value));
+    }
+}
+
+impl MergeableNode for Node {
+    type Key = &'static str;
+    type Mapping = Map;
+    type Tag = &'static str;
+    type Meta = ();
+
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self> {
+        let kind = match self {
+            Node::Map(map) => NodeKind::Mapping(map),
+            Node::List(list) => NodeKind::Sequence(list),
+            Node::Tagged(tag, node) => NodeKind::Tagged(tag, node),
+            Node::Ref(id) => NodeKind::Alias(id),
+            Node::Scalar(_) => return Err(self),
+        };
+        Ok((kind, ()))
+    }
+
+    fn from_parts(kind: NodeKind<Self>, _: Self::Meta) -> Self {
+        match kind {
+            NodeKind::Mapping(map) => Node::Map(map),
+            NodeKind::Sequence(list) => Node::List(list),
+            NodeKind::Tagged(tag, node) => Node::Tagged(tag, node),
+            NodeKind::Alias(id) => Node::Ref(id),
+            NodeKind::Undefined => Node::Scalar("undefined"),
+        }
+    }
+
+    fn is_merge_key(key: &Self::Key) -> bool {
+        *key == "<<"
+    }
+
+    fn merge_in_key<E, F>(key: Self::Key, _: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>,
+    {
+        Ok(key)
+    }
+}
+
+fn map(entries: Vec<(&'static str, Node)>) -> Node {
+    Node::Map(Map(entries))
+}
+
+#[test]
+fn test_custom_node_merge_keys() {
+    let doc = Node::List(vec![map(vec![
+        (
+            "<<",
+            map(vec![("a", Node::Scalar("0")), ("c", Node::Scalar("3"))]),
+        ),
+        ("a", Node::Scalar("1")),
+        ("b", Node::Scalar("2")),
+    ])]);
+    let expected = Node::List(vec![map(vec![
+        ("a", Node::Scalar("1")),
+        ("b", Node::Scalar("2")),
+        ("c", Node::Scalar("3")),
+    ])]);
+
+    assert_eq!(merge_keys(doc).unwrap(), expected);
+}
+
+#[test]
+fn test_custom_node_tagged() {
+    let doc = Node::Tagged(
+        "!tag",
+        Box::new(map(vec![
+            ("<<", map(vec![("b", Node::Scalar("2"))])),
+            ("a", Node::Scalar("1")),
+        ])),
+    );
+    let expected = Node::Tagged(
+        "!tag",
+        Box::new(map(vec![
+            ("a", Node::Scalar("1")),
+            ("b", Node::Scalar("2")),
+        ])),
+    );
+
+    assert_eq!(merge_keys(doc).unwrap(), expected);
+}
+
+#[test]
+fn test_custom_node_anchors() {
+    let anchors = vec![(0, map(vec![("b", Node::Scalar("2"))]))]
+        .into_iter()
+        .collect::<AnchorTable<_>>();
+    let doc = map(vec![("<<", Node::Ref(0)), ("a", Node::Scalar("1"))]);
+    let expected = map(vec![("a", Node::Scalar("1")), ("b", Node::Scalar("2"))]);
+
+    assert_eq!(merge_keys_with_anchors(doc, &anchors).unwrap(), expected);
+}
+
+#[test]
+fn test_custom_node_invalid_merge_value() {
+    let doc = map(vec![("<<", Node::Scalar("0"))]);
+    let err = merge_keys(doc).unwrap_err();
+
+    if !matches!(err, MergeKeyError::InvalidMergeValue) {
+        panic!("unexpected error: {:?}", err);
+    }
+}
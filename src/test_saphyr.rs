@@ -0,0 +1,281 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use saphyr::{LoadableYamlNode, Mapping, MarkedYaml, Yaml};
+
+use crate::saphyr_yaml::{merge_keys_saphyr, merge_keys_saphyr_marked};
+use crate::{merge_keys_with_options, AnchorTable, MergeKeyError, MergeOptions, TagPolicy};
+
+fn load(source: &str) -> Yaml<'static> {
+    Yaml::load_from_str(source).unwrap().remove(0)
+}
+
+fn load_marked(source: &str) -> MarkedYaml<'static> {
+    MarkedYaml::load_from_str(source).unwrap().remove(0)
+}
+
+fn assert_yaml_idempotent(doc: Yaml) {
+    assert_eq!(merge_keys_saphyr(doc.clone()).unwrap(), doc);
+}
+
+fn assert_merged(raw: &str, merged: &str) {
+    assert_eq!(merge_keys_saphyr(load(raw)).unwrap(), load(merged));
+}
+
+#[test]
+fn test_ignore_non_containers() {
+    let null = load("~");
+    let bool_true = load("true");
+    let bool_false = load("false");
+    let string = load("''");
+    let integer = load("1234");
+    let real = load("0.02");
+    let tagged = load("!tag value");
+
+    assert_yaml_idempotent(null);
+    assert_yaml_idempotent(bool_true);
+    assert_yaml_idempotent(bool_false);
+    assert_yaml_idempotent(string);
+    assert_yaml_idempotent(integer);
+    assert_yaml_idempotent(real);
+    assert_yaml_idempotent(tagged);
+}
+
+#[test]
+fn test_ignore_container_no_merge_keys() {
+    let arr = load("[10, 100]");
+    let hash = load("{10: ~, 100: string}");
+
+    assert_yaml_idempotent(arr);
+    assert_yaml_idempotent(hash);
+}
+
+#[test]
+fn test_remove_merge_keys() {
+    assert_merged("{<<: {}, 10: ~, 100: string}", "{10: ~, 100: string}");
+}
+
+#[test]
+fn test_handle_merge_keys() {
+    assert_merged(
+        "{<<: {15: ~}, 10: ~, 100: string}",
+        "{10: ~, 100: string, 15: ~}",
+    );
+}
+
+#[test]
+fn test_merge_key_precedence() {
+    assert_merged("{<<: {10: 10}, 10: ~, 100: string}", "{10: ~, 100: string}");
+}
+
+#[test]
+fn test_merge_key_array() {
+    assert_merged(
+        "{<<: [{15: 10}, {20: 10}], 10: ~, 100: string}",
+        "{10: ~, 100: string, 15: 10, 20: 10}",
+    );
+}
+
+#[test]
+fn test_merge_key_array_precedence() {
+    assert_merged(
+        "{<<: [{15: 10}, {15: 20}], 10: ~, 100: string}",
+        "{10: ~, 100: string, 15: 10}",
+    );
+}
+
+#[test]
+fn test_merge_key_nested_array() {
+    assert_merged(
+        "[{<<: [{15: 10}, {15: 20}], 10: ~, 100: string}]",
+        "[{10: ~, 100: string, 15: 10}]",
+    );
+}
+
+#[test]
+fn test_merge_key_nested_hash_value() {
+    assert_merged(
+        "{~: {<<: [{15: 10}, {15: 20}], 10: ~, 100: string}}",
+        "{~: {10: ~, 100: string, 15: 10}}",
+    );
+}
+
+#[test]
+fn test_merge_key_nested_hash_key() {
+    assert_merged(
+        "{? {<<: [{15: 10}, {15: 20}], 10: ~, 100: string} : ~}",
+        "{? {10: ~, 100: string, 15: 10} : ~}",
+    );
+}
+
+#[test]
+fn test_merge_key_tagged_value() {
+    assert_merged("!tag {<<: {15: 10}, 10: ~}", "!tag {10: ~, 15: 10}");
+}
+
+#[test]
+fn test_merge_key_tagged_merge_source() {
+    assert_merged(
+        "!tag {<<: [!a {15: 10}, !b {20: 10}], 10: ~}",
+        "!tag {10: ~, 15: 10, 20: 10}",
+    );
+}
+
+#[test]
+fn test_merge_key_tag_policy() {
+    let inherit = MergeOptions::new().with_tag_policy(TagPolicy::InheritFirst);
+    let require = MergeOptions::new().with_tag_policy(TagPolicy::RequireMatch);
+    let merge = |raw, options| merge_keys_with_options(load(raw), &AnchorTable::new(), options);
+
+    assert_eq!(
+        merge("{<<: !a {15: 10}, 10: ~}", &inherit).unwrap(),
+        load("!a {10: ~, 15: 10}"),
+    );
+    assert_eq!(
+        merge("!b {<<: !b {15: 10}, 10: ~}", &require).unwrap(),
+        load("!b {10: ~, 15: 10}"),
+    );
+    assert!(matches!(
+        merge("!b {<<: !a {15: 10}, 10: ~}", &require),
+        Err(MergeKeyError::TagMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_yaml_spec_examples() {
+    let raw = "\
+- &CENTER { x: 1, y: 2 }
+- &LEFT { x: 0, y: 2 }
+- &BIG { r: 10 }
+- &SMALL { r: 1 }
+- x: 1
+  y: 2
+  r: 10
+  label: center/big
+- << : *CENTER
+  r: 10
+  label: center/big
+- << : [ *CENTER, *BIG ]
+  label: center/big
+- << : [ *BIG, *LEFT, *SMALL ]
+  x: 1
+  label: center/big
+";
+    let sorted_entries = |map: &Yaml<'static>| {
+        let mut entries = map
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<_>>();
+        entries.sort();
+        entries
+    };
+    let merged = merge_keys_saphyr(load(raw)).unwrap();
+    let explicit = sorted_entries(&merged[4]);
+
+    assert_eq!(sorted_entries(&merged[5]), explicit);
+    assert_eq!(sorted_entries(&merged[6]), explicit);
+    assert_eq!(sorted_entries(&merged[7]), explicit);
+}
+
+#[test]
+fn test_marked_spans() {
+    let raw = "\
+base: &base
+  inherited: 1
+dict:
+  <<: *base
+  local: 2
+";
+    let merged = merge_keys_saphyr_marked(load_marked(raw)).unwrap();
+    let dict = merged
+        .data
+        .as_mapping()
+        .unwrap()
+        .iter()
+        .find(|(k, _)| k.data.as_str() == Some("dict"))
+        .map(|(_, v)| v)
+        .unwrap();
+    let span_of = |key: &str| {
+        dict.data
+            .as_mapping()
+            .unwrap()
+            .iter()
+            .find(|(k, _)| k.data.as_str() == Some(key))
+            .map(|(_, v)| v.span)
+            .unwrap()
+    };
+
+    assert_eq!(dict.span.start.line(), 4);
+    assert_eq!(span_of("local").start.line(), 5);
+    // Merged values keep the span from their anchored source.
+    assert_eq!(span_of("inherited").start.line(), 2);
+}
+
+macro_rules! assert_is_error {
+    ( $doc:expr, $kind:pat ) => {
+        let err = merge_keys_saphyr($doc).unwrap_err();
+
+        if !matches!(err, $kind) {
+            panic!("unexpected error: {:?}", err);
+        }
+    };
+}
+
+#[test]
+fn test_invalid_merge_key_values() {
+    let merge_null = load("{<<: ~}");
+    let merge_bool = load("{<<: false}");
+    let merge_string = load("{<<: ''}");
+    let merge_integer = load("{<<: 0}");
+    let merge_real = load("{<<: 0.02}");
+
+    assert_is_error!(merge_null, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_bool, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_string, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_integer, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_real, MergeKeyError::InvalidMergeValue);
+}
+
+#[test]
+fn test_invalid_merge_key_array_values() {
+    let merge_null = load("{<<: [~]}");
+    let merge_bool = load("{<<: [false]}");
+    let merge_string = load("{<<: ['']}");
+    let merge_integer = load("{<<: [0]}");
+    let merge_real = load("{<<: [0.02]}");
+
+    assert_is_error!(merge_null, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_bool, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_string, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_integer, MergeKeyError::InvalidMergeValue);
+    assert_is_error!(merge_real, MergeKeyError::InvalidMergeValue);
+}
+
+#[test]
+fn test_undefined_alias_merge_value() {
+    let mut merge_bad = Mapping::new();
+    merge_bad.insert(load("<<"), Yaml::BadValue);
+
+    assert_is_error!(
+        Yaml::Mapping(merge_bad),
+        MergeKeyError::UndefinedAlias {
+            anchor: None,
+            marker: None,
+        }
+    );
+}
+
+#[test]
+fn test_unresolved_alias() {
+    let mut merge_arr = Mapping::new();
+    merge_arr.insert(load("<<"), Yaml::Sequence(vec![Yaml::Alias(1)]));
+    let arr = Yaml::Sequence(vec![Yaml::Alias(1)]);
+
+    assert_is_error!(Yaml::Mapping(merge_arr), MergeKeyError::UnresolvedAlias(1));
+    assert_eq!(merge_keys_saphyr(arr.clone()).unwrap(), arr);
+}
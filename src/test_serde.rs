@@ -6,7 +6,8 @@
 
 use serde_yaml::Value;
 
-use crate::serde::{merge_keys_serde, TAGGED_YAML_SMUGGLE_TAG_KEY, TAGGED_YAML_SMUGGLE_VALUE_KEY};
+use crate::serde::{merge_keys_serde, merge_keys_serde_with_options};
+use crate::{MergeKeyError, MergeOptions, TagPolicy};
 
 fn assert_yaml_idempotent(doc: Value) {
     assert_eq!(merge_keys_serde(doc.clone()).unwrap(), doc);
@@ -50,6 +51,44 @@ fn test_float_constants() {
     assert_yaml_idempotent(neg_inf);
 }
 
+#[test]
+fn test_large_integers() {
+    let u64_max = Value::Number(u64::MAX.into());
+    let above_i64 = Value::Number((i64::MAX as u64 + 1).into());
+    let i64_min = Value::Number(i64::MIN.into());
+
+    assert_yaml_idempotent(u64_max);
+    assert_yaml_idempotent(above_i64);
+    assert_yaml_idempotent(i64_min);
+}
+
+#[test]
+fn test_numbers_are_preserved() {
+    let raw = "\
+base: &base
+  id: 18446744073709551615
+  ratio: 1.0e-7
+  big: 1.7976931348623157e308
+dict:
+  <<: *base
+  local: 9223372036854775808
+";
+    let doc: Value = serde_yaml::from_str(raw).unwrap();
+    let merged = merge_keys_serde(doc.clone()).unwrap();
+
+    for key in ["id", "ratio", "big"] {
+        let (merged, source) = match (&merged["dict"][key], &doc["base"][key]) {
+            (Value::Number(merged), Value::Number(source)) => (merged, source),
+            values => panic!("unexpected values: {:?}", values),
+        };
+        assert_eq!(merged, source);
+        assert_eq!(merged.to_string(), source.to_string());
+    }
+    assert_eq!(merged["dict"]["id"].as_u64(), Some(u64::MAX));
+    assert_eq!(merged["dict"]["local"].as_u64(), Some(i64::MAX as u64 + 1));
+    assert!(merged["dict"]["local"].is_u64());
+}
+
 #[test]
 fn test_ignore_container_no_merge_keys() {
     let arr = Value::Sequence(vec![Value::Number(10.into()), Value::Number(100.into())]);
@@ -96,6 +135,34 @@ fn test_handle_merge_keys() {
     assert_eq!(merge_keys_serde(hash).unwrap(), expected);
 }
 
+#[test]
+fn test_merge_keys_keep_order() {
+    let hash = yaml_hash![
+        (Value::Number(1.into()), Value::Null),
+        (
+            merge_key(),
+            yaml_hash![(Value::Number(3.into()), Value::Null)],
+        ),
+        (Value::Number(2.into()), Value::Null),
+    ];
+
+    // Mapping equality ignores order, so compare the keys.
+    let merged = merge_keys_serde(hash).unwrap();
+    let keys = merged
+        .as_mapping()
+        .unwrap()
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [1, 2, 3]
+            .iter()
+            .map(|&n| Value::Number(n.into()))
+            .collect::<Vec<_>>(),
+    );
+}
+
 #[test]
 fn test_merge_key_precedence() {
     let hash = yaml_hash![
@@ -315,16 +382,12 @@ fn test_yaml_spec_examples() {
 }
 
 macro_rules! assert_is_error {
-    ( $doc:expr, $kind:path ) => {
-        let _ = merge_keys_serde($doc).unwrap_err();
+    ( $doc:expr, $kind:pat ) => {
+        let err = merge_keys_serde($doc).unwrap_err();
 
-        /* XXX: irrefutable
-        if let $kind = err {
-            // Expected error.
-        } else {
+        if !matches!(err, $kind) {
             panic!("unexpected error: {:?}", err);
         }
-        */
     };
 }
 
@@ -378,53 +441,34 @@ fn test_tagged_value() {
 }
 
 #[test]
-fn test_tagged_value_imposter() {
-    let fake = yaml_hash![
-        (
-            Value::String(TAGGED_YAML_SMUGGLE_TAG_KEY.into()),
-            Value::String("tag".into()),
-        ),
+fn test_tagged_value_lookalike() {
+    // Mappings which look like the tagged value encoding used by earlier versions are left alone.
+    let lookalike = yaml_hash![
         (
-            Value::String(TAGGED_YAML_SMUGGLE_VALUE_KEY.into()),
-            Value::Null,
-        ),
-    ];
-    let fake_false_positive = yaml_tagged!("tag" => Value::Null);
-    let wrong_count_just_tag = yaml_hash![(
-        Value::String(TAGGED_YAML_SMUGGLE_TAG_KEY.into()),
-        Value::String("tag".into()),
-    ),];
-    let wrong_count_just_value = yaml_hash![(
-        Value::String(TAGGED_YAML_SMUGGLE_VALUE_KEY.into()),
-        Value::Null,
-    ),];
-    let wrong_count_more = yaml_hash![
-        (
-            Value::String(TAGGED_YAML_SMUGGLE_TAG_KEY.into()),
+            Value::String("70235535-46bb-46f9-b535-31596b77733f".into()),
             Value::String("tag".into()),
         ),
         (
-            Value::String(TAGGED_YAML_SMUGGLE_VALUE_KEY.into()),
-            Value::Null,
-        ),
-        (Value::String("other".into()), Value::Null),
-    ];
-    let wrong_tag_type = yaml_hash![
-        (
-            Value::String(TAGGED_YAML_SMUGGLE_TAG_KEY.into()),
-            Value::Null,
-        ),
-        (
-            Value::String(TAGGED_YAML_SMUGGLE_VALUE_KEY.into()),
+            Value::String("b5282e53-aaaf-4f67-a0b5-d5bb3bd0d4d2".into()),
             Value::Null,
         ),
     ];
 
-    assert_eq!(merge_keys_serde(fake).unwrap(), fake_false_positive);
-    assert_yaml_idempotent(wrong_count_just_tag);
-    assert_yaml_idempotent(wrong_count_just_value);
-    assert_yaml_idempotent(wrong_count_more);
-    assert_yaml_idempotent(wrong_tag_type);
+    assert_yaml_idempotent(lookalike);
+}
+
+#[test]
+fn test_merge_key_nested_tagged_value() {
+    let tagged = yaml_tagged!("Tag" => yaml_hash![
+        (merge_key(), yaml_hash![(Value::Number(15.into()), Value::Null)]),
+        (Value::Number(10.into()), Value::Null),
+    ]);
+    let expected = yaml_tagged!("Tag" => yaml_hash![
+        (Value::Number(10.into()), Value::Null),
+        (Value::Number(15.into()), Value::Null),
+    ]);
+
+    assert_eq!(merge_keys_serde(tagged).unwrap(), expected);
 }
 
 #[test]
@@ -451,3 +495,117 @@ fn test_merge_tagged_value() {
 
     assert_eq!(merge_keys_serde(hash).unwrap(), expected);
 }
+
+#[test]
+fn test_merge_tagged_merge_source() {
+    let raw = "\
+base: &base !Custom
+  a: 1
+dict:
+  <<: *base
+  b: 2
+list:
+  <<: [*base, !Other {c: 3}]
+";
+    let expected = "\
+base: !Custom
+  a: 1
+dict:
+  b: 2
+  a: 1
+list:
+  a: 1
+  c: 3
+";
+    let doc: Value = serde_yaml::from_str(raw).unwrap();
+    let expected: Value = serde_yaml::from_str(expected).unwrap();
+
+    assert_eq!(merge_keys_serde(doc).unwrap(), expected);
+}
+
+#[test]
+fn test_merge_tagged_merge_source_keep_local() {
+    let doc: Value = serde_yaml::from_str("!Local {<<: !Custom {a: 1}, b: 2}").unwrap();
+    let expected: Value = serde_yaml::from_str("!Local {b: 2, a: 1}").unwrap();
+    let options = MergeOptions::new().with_tag_policy(TagPolicy::KeepLocal);
+
+    assert_eq!(
+        merge_keys_serde_with_options(doc, &options).unwrap(),
+        expected,
+    );
+}
+
+#[test]
+fn test_merge_tagged_merge_source_reject() {
+    let options = MergeOptions::new().with_tag_policy(TagPolicy::Reject);
+    let merge_tagged: Value = serde_yaml::from_str("{<<: !Custom {a: 1}}").unwrap();
+    let merge_tagged_array: Value = serde_yaml::from_str("{<<: [{a: 1}, !Custom {b: 1}]}").unwrap();
+
+    let err = merge_keys_serde_with_options(merge_tagged, &options).unwrap_err();
+    assert!(matches!(&err, MergeKeyError::TaggedMergeValue(tag) if tag == "!Custom"));
+    assert_eq!(err.to_string(), "tagged value `!Custom` may not be merged");
+    let err = merge_keys_serde_with_options(merge_tagged_array, &options).unwrap_err();
+    assert!(matches!(&err, MergeKeyError::TaggedMergeValue(tag) if tag == "!Custom"));
+}
+
+#[test]
+fn test_merge_tagged_scalar() {
+    let merge_tagged: Value = serde_yaml::from_str("{<<: !Custom 1}").unwrap();
+
+    assert_is_error!(merge_tagged, MergeKeyError::InvalidMergeValue);
+}
+
+#[test]
+fn test_merge_tagged_merge_source_inherit_first() {
+    let raw = "\
+- {<<: [!Template {a: 1}, !Other {b: 2}], c: 3}
+- {<<: [{a: 1}, !Other {b: 2}], c: 3}
+- !Job {<<: !Template {a: 1}, c: 3}
+- {<<: {a: 1}, c: 3}
+";
+    let expected = "\
+- !Template {c: 3, a: 1, b: 2}
+- {c: 3, a: 1, b: 2}
+- !Job {c: 3, a: 1}
+- {c: 3, a: 1}
+";
+    let doc: Value = serde_yaml::from_str(raw).unwrap();
+    let expected: Value = serde_yaml::from_str(expected).unwrap();
+    let options = MergeOptions::new().with_tag_policy(TagPolicy::InheritFirst);
+
+    assert_eq!(
+        merge_keys_serde_with_options(doc, &options).unwrap(),
+        expected,
+    );
+}
+
+#[test]
+fn test_merge_tagged_merge_source_require_match() {
+    let options = MergeOptions::new().with_tag_policy(TagPolicy::RequireMatch);
+    let matching: Value = serde_yaml::from_str("!Job {<<: [!Job {a: 1}, {b: 2}], c: 3}").unwrap();
+    let expected: Value = serde_yaml::from_str("!Job {c: 3, a: 1, b: 2}").unwrap();
+    let mismatch: Value = serde_yaml::from_str("!Job {<<: !Template {a: 1}}").unwrap();
+    let untagged: Value = serde_yaml::from_str("{<<: !Template {a: 1}}").unwrap();
+
+    assert_eq!(
+        merge_keys_serde_with_options(matching, &options).unwrap(),
+        expected,
+    );
+    let err = merge_keys_serde_with_options(mismatch, &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "merge source tagged `!Template` does not match `!Job`",
+    );
+    let err = merge_keys_serde_with_options(untagged, &options).unwrap_err();
+    assert!(matches!(
+        &err,
+        MergeKeyError::TagMismatch {
+            local: None,
+            merged,
+        } if merged == "!Template"
+    ));
+    assert_eq!(
+        err.to_string(),
+        "merge source tagged `!Template` does not match the untagged mapping",
+    );
+}
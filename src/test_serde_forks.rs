@@ -0,0 +1,81 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+macro_rules! serde_fork_tests {
+    ( $module:ident, $krate:ident, $merge_keys:ident, $merge_keys_with_options:ident $(,)? ) => {
+        mod $module {
+            use $krate::Value;
+
+            use crate::{
+                $merge_keys, $merge_keys_with_options, MergeKeyError, MergeOptions, TagPolicy,
+            };
+
+            fn load(raw: &str) -> Value {
+                $krate::from_str(raw).unwrap()
+            }
+
+            #[test]
+            fn test_merge_keys() {
+                let raw = "\
+base: &base
+  id: 18446744073709551615
+  label: base
+dict:
+  <<: *base
+  label: dict
+tagged: !Local
+  <<: !Custom {a: 1}
+";
+                let expected = "\
+base:
+  id: 18446744073709551615
+  label: base
+dict:
+  label: dict
+  id: 18446744073709551615
+tagged: !Local
+  a: 1
+";
+                let merged = $merge_keys(load(raw)).unwrap();
+
+                assert_eq!(merged, load(expected));
+                assert_eq!(merged["dict"]["id"].as_u64(), Some(u64::MAX));
+            }
+
+            #[test]
+            fn test_merge_keys_with_options() {
+                let options = MergeOptions::new().with_tag_policy(TagPolicy::Reject);
+                let err = $merge_keys_with_options(load("{<<: !Custom {a: 1}}"), &options)
+                    .unwrap_err();
+
+                assert!(matches!(&err, MergeKeyError::TaggedMergeValue(tag) if tag == "!Custom"));
+            }
+
+            #[test]
+            fn test_invalid_merge_value() {
+                let err = $merge_keys(load("{<<: 1}")).unwrap_err();
+
+                assert!(matches!(err, MergeKeyError::InvalidMergeValue));
+            }
+        }
+    };
+}
+
+#[cfg(feature = "serde_norway")]
+serde_fork_tests!(
+    norway,
+    serde_norway,
+    merge_keys_serde_norway,
+    merge_keys_serde_norway_with_options,
+);
+
+#[cfg(feature = "serde_yml")]
+serde_fork_tests!(
+    yml,
+    serde_yml,
+    merge_keys_serde_yml,
+    merge_keys_serde_yml_with_options,
+);
@@ -0,0 +1,114 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::convert::Infallible;
+use std::mem;
+
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::Yaml;
+
+use crate::node::{MergeableMapping, MergeableNode, NodeKind, MERGE_KEY};
+use crate::MergeKeyError;
+
+impl MergeableMapping for Hash {
+    type Key = Yaml;
+    type Value = Yaml;
+
+    fn contains_key(&self, key: &Self::Key) -> bool {
+        Hash::contains_key(self, key)
+    }
+
+    fn insert(&mut self, key: Self::Key, value: Self::Value) {
+        // `hashlink`'s `Entry::or_insert` moves existing entries to the back, so only plain
+        // insertion of new keys is used.
+        Hash::insert(self, key, value);
+    }
+
+    fn merge_in_place<E, F>(
+        &mut self,
+        is_merge_key: fn(&Self::Key) -> bool,
+        mut merge: F,
+    ) -> Option<Result<Option<Self::Value>, E>>
+    where
+        F: FnMut(&Self::Key, Self::Value) -> Result<Self::Value, E>,
+    {
+        let is_node = |key: &Yaml| {
+            matches!(
+                key,
+                Yaml::Hash(_) | Yaml::Array(_) | Yaml::Alias(_) | Yaml::BadValue,
+            )
+        };
+        if self.keys().any(is_node) {
+            return None;
+        }
+
+        let mut merge_value = None;
+        self.retain(|key, value| {
+            if is_merge_key(key) {
+                merge_value = Some(mem::replace(value, Yaml::Null));
+                false
+            } else {
+                true
+            }
+        });
+        let res = self.iter_mut().try_for_each(|(key, value)| {
+            *value = merge(key, mem::replace(value, Yaml::Null))?;
+            Ok(())
+        });
+        Some(res.map(|()| merge_value))
+    }
+}
+
+impl MergeableNode for Yaml {
+    type Key = Yaml;
+    type Mapping = Hash;
+    type Tag = Infallible;
+    type Meta = ();
+
+    fn into_parts(self) -> Result<(NodeKind<Self>, Self::Meta), Self> {
+        let kind = match self {
+            Yaml::Hash(hash) => NodeKind::Mapping(hash),
+            Yaml::Array(arr) => NodeKind::Sequence(arr),
+            Yaml::Alias(id) => NodeKind::Alias(id),
+            Yaml::BadValue => NodeKind::Undefined,
+            _ => return Err(self),
+        };
+        Ok((kind, ()))
+    }
+
+    fn from_parts(kind: NodeKind<Self>, _: Self::Meta) -> Self {
+        match kind {
+            NodeKind::Mapping(hash) => Yaml::Hash(hash),
+            NodeKind::Sequence(arr) => Yaml::Array(arr),
+            NodeKind::Tagged(tag, _) => match tag {},
+            NodeKind::Alias(id) => Yaml::Alias(id),
+            NodeKind::Undefined => Yaml::BadValue,
+        }
+    }
+
+    fn is_merge_key(key: &Self::Key) -> bool {
+        matches!(key, Yaml::String(s) if s == MERGE_KEY)
+    }
+
+    fn merge_in_key<E, F>(key: Self::Key, merge: F) -> Result<Self::Key, E>
+    where
+        F: FnOnce(Self) -> Result<Self, E>,
+    {
+        merge(key)
+    }
+}
+
+/// Handle merge keys in a `yaml-rust2` YAML document.
+///
+/// `yaml-rust2`'s loader expands aliases, so any remaining `Yaml::Alias` node is reported as an
+/// unresolved alias.
+///
+/// `yaml-rust2` has no document type which keeps source positions (unlike `saphyr`'s
+/// `MarkedYaml`), so only `Yaml` documents are supported. Use `merge_keys_saphyr_marked` with the
+/// `saphyr` feature to keep spans of merged entries.
+pub fn merge_keys_yaml_rust2(doc: Yaml) -> Result<Yaml, MergeKeyError> {
+    crate::merge_keys(doc)
+}